@@ -0,0 +1,152 @@
+// In this file it is implemented the clocks that drive the
+// simulation time. The control module owns a clock and asks it,
+// after every timestep, which timestep comes next.
+use std::sync::{Arc, Mutex};
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+use crate::time::{SimDuration, SimTime};
+
+pub trait Clock {
+    // Returns the timestep the simulation should move to, given the
+    // current one and the time of the next pending event (None if
+    // the event list is empty). Returning None stops the run.
+    fn next_timestep(&mut self, current_timestep: SimTime, next_event: Option<SimTime>) -> Option<SimTime>;
+}
+
+// Jumps straight to the next pending event, without waiting for
+// the host. The run ends when the event list is exhausted.
+#[derive(Debug, Default)]
+pub struct VirtualClock;
+
+impl VirtualClock {
+    pub fn new() -> Self {
+        VirtualClock
+    }
+}
+
+impl Clock for VirtualClock {
+    fn next_timestep(&mut self, _current_timestep: SimTime, next_event: Option<SimTime>) -> Option<SimTime> {
+        next_event
+    }
+}
+
+// Paces the simulation against the host clock: every timestep
+// lasts timestep_duration of real time. When the event list is
+// empty the clock keeps ticking one timestep at a time, so events
+// injected at runtime are still handled, until end_timestep.
+#[derive(Debug)]
+pub struct PacedClock {
+    timestep_duration: Duration,
+    end_timestep: SimTime,
+    origin: Option<(Instant, SimTime)>,
+}
+
+impl PacedClock {
+    pub fn new(timestep_duration: Duration, end_timestep: impl Into<SimTime>) -> Self {
+        PacedClock { timestep_duration, end_timestep: end_timestep.into(), origin: None }
+    }
+}
+
+impl Clock for PacedClock {
+    fn next_timestep(&mut self, current_timestep: SimTime, next_event: Option<SimTime>) -> Option<SimTime> {
+        let (origin, origin_timestep) = *self.origin.get_or_insert((Instant::now(), current_timestep));
+
+        let next = next_event.unwrap_or(current_timestep + SimDuration::from(1));
+        if next >= self.end_timestep {
+            return None;
+        }
+
+        // Sleep until the wall clock reaches the desired instant. A
+        // wait too long to be represented stops the clock.
+        let elapsed = u32::try_from((next - origin_timestep).ticks()).ok()?;
+        let due = origin.checked_add(self.timestep_duration.checked_mul(elapsed)?)?;
+        let now = Instant::now();
+        if due > now {
+            sleep(due - now);
+        }
+        Some(next)
+    }
+}
+
+// Driven from outside the simulation: the embedder moves the
+// target forward with advance_to and runs the control module
+// again. The run pauses once every event up to the target has
+// been handled, leaving the current timestep at the target.
+#[derive(Debug, Clone, Default)]
+pub struct ExternalClock {
+    target: Arc<Mutex<SimTime>>,
+}
+
+impl ExternalClock {
+    pub fn new() -> Self {
+        ExternalClock { target: Arc::new(Mutex::new(SimTime::ZERO)) }
+    }
+
+    pub fn advance_to(&self, timestep: impl Into<SimTime>) {
+        let timestep = timestep.into();
+        let mut target = self.target.lock().unwrap();
+        if timestep > *target {
+            *target = timestep;
+        }
+    }
+
+    pub fn target(&self) -> SimTime {
+        *self.target.lock().unwrap()
+    }
+}
+
+impl Clock for ExternalClock {
+    fn next_timestep(&mut self, current_timestep: SimTime, next_event: Option<SimTime>) -> Option<SimTime> {
+        let target = self.target();
+        match next_event {
+            Some(time) if time <= target => Some(time),
+            _ if current_timestep < target => Some(target),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn t(ticks: i32) -> SimTime {
+        SimTime::from(ticks)
+    }
+
+    #[test]
+    fn test_virtual_clock_jumps_to_next_event() {
+        let mut clock = VirtualClock::new();
+
+        assert_eq!(clock.next_timestep(t(0), Some(t(240))), Some(t(240)));
+        assert_eq!(clock.next_timestep(t(240), None), None);
+    }
+
+    #[test]
+    fn test_paced_clock_stops_at_end() {
+        let mut clock = PacedClock::new(Duration::from_millis(0), 10);
+
+        assert_eq!(clock.next_timestep(t(0), None), Some(t(1)));
+        assert_eq!(clock.next_timestep(t(1), Some(t(5))), Some(t(5)));
+        assert_eq!(clock.next_timestep(t(5), Some(t(10))), None);
+
+        // A wait the wall clock cannot represent stops the run
+        let mut clock = PacedClock::new(Duration::MAX, SimTime::from_ticks(i64::MAX));
+        assert_eq!(clock.next_timestep(t(0), Some(t(2))), None);
+        assert_eq!(clock.next_timestep(t(0), Some(SimTime::from_ticks(1 << 40))), None);
+    }
+
+    #[test]
+    fn test_external_clock_pauses_at_target() {
+        let handle = ExternalClock::new();
+        let mut clock = handle.clone();
+        handle.advance_to(50);
+
+        assert_eq!(clock.next_timestep(t(0), Some(t(20))), Some(t(20)));
+        assert_eq!(clock.next_timestep(t(20), Some(t(80))), Some(t(50)));
+        assert_eq!(clock.next_timestep(t(50), Some(t(80))), None);
+
+        handle.advance_to(100);
+        assert_eq!(clock.next_timestep(t(50), Some(t(80))), Some(t(80)));
+    }
+}
@@ -0,0 +1,256 @@
+// In this file it is implemented the channels jobs use to exchange
+// data: pipes, whose buffer is measured in bytes, and message
+// queues, whose buffer is measured in messages. Both are bounded: a
+// sender finding the buffer full and a receiver finding no message
+// ready wait in the channel until the other side makes progress.
+// Every message takes the channel latency to become available.
+use crate::time::{SimDuration, SimTime};
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ChannelError {
+    UnknownChannel(String),
+    // The message can never fit the channel buffer
+    MessageTooLarge(String, i32),
+}
+
+impl fmt::Display for ChannelError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChannelError::UnknownChannel(name) => write!(f, "canal {} nao existe", name),
+            ChannelError::MessageTooLarge(name, size) => {
+                write!(f, "mensagem de tamanho {} nao cabe no canal {}", size, name)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChannelKind {
+    // Capacity in bytes
+    Pipe,
+    // Capacity in messages
+    MessageQueue,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Message {
+    pub sender: i32,
+    pub size: i32,
+    pub sent_at: SimTime,
+    // Instant the message becomes available to receivers
+    pub ready_at: SimTime,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Channel {
+    pub kind: ChannelKind,
+    pub capacity: i32,
+    pub latency: SimDuration,
+    buffer: VecDeque<Message>,
+    // (job, message size, blocked since)
+    blocked_senders: VecDeque<(i32, i32, SimTime)>,
+    // (job, blocked since)
+    blocked_receivers: VecDeque<(i32, SimTime)>,
+}
+
+impl Channel {
+    fn new(kind: ChannelKind, capacity: i32, latency: SimDuration) -> Self {
+        Channel {
+            kind,
+            capacity,
+            latency,
+            buffer: VecDeque::new(),
+            blocked_senders: VecDeque::new(),
+            blocked_receivers: VecDeque::new(),
+        }
+    }
+
+    // Buffer space a message of the given size takes
+    fn cost(&self, size: i32) -> i32 {
+        match self.kind {
+            ChannelKind::Pipe => size,
+            ChannelKind::MessageQueue => 1,
+        }
+    }
+
+    fn used(&self) -> i32 {
+        self.buffer.iter().map(|message| self.cost(message.size)).sum()
+    }
+
+    fn fits(&self, size: i32) -> bool {
+        self.used() + self.cost(size) <= self.capacity
+    }
+
+    fn enqueue(&mut self, sender: i32, size: i32, now: SimTime) {
+        let ready_at = now + self.latency;
+        self.buffer.push_back(Message { sender, size, sent_at: now, ready_at });
+    }
+
+    fn ready_message(&mut self, now: SimTime) -> Option<Message> {
+        if self.buffer.front().is_some_and(|message| message.ready_at <= now) {
+            self.buffer.pop_front()
+        } else {
+            None
+        }
+    }
+
+    pub fn buffered(&self) -> usize {
+        self.buffer.len()
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ChannelManager {
+    channels: BTreeMap<String, Channel>,
+}
+
+impl ChannelManager {
+    pub fn new() -> Self {
+        ChannelManager { channels: BTreeMap::new() }
+    }
+
+    pub fn create_channel(&mut self, name: String, kind: ChannelKind, capacity: i32, latency: SimDuration) {
+        self.channels.insert(name, Channel::new(kind, capacity, latency));
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Channel> {
+        self.channels.get(name)
+    }
+
+    fn channel(&mut self, name: &str) -> Result<&mut Channel, ChannelError> {
+        self.channels.get_mut(name).ok_or_else(|| ChannelError::UnknownChannel(name.to_string()))
+    }
+
+    // Returns the ready time of the message if it went into the
+    // buffer, or None if the sender has to wait for space
+    pub fn send(&mut self, name: &str, job_id: i32, size: i32, now: SimTime) -> Result<Option<SimTime>, ChannelError> {
+        let channel = self.channel(name)?;
+        if channel.cost(size) > channel.capacity {
+            return Err(ChannelError::MessageTooLarge(name.to_string(), size));
+        }
+        if channel.blocked_senders.is_empty() && channel.fits(size) {
+            channel.enqueue(job_id, size, now);
+            Ok(Some(now + channel.latency))
+        } else {
+            channel.blocked_senders.push_back((job_id, size, now));
+            Ok(None)
+        }
+    }
+
+    // Returns the message received, or None if the receiver has to
+    // wait for one
+    pub fn receive(&mut self, name: &str, job_id: i32, now: SimTime) -> Result<Option<Message>, ChannelError> {
+        let channel = self.channel(name)?;
+        if channel.blocked_receivers.is_empty() {
+            if let Some(message) = channel.ready_message(now) {
+                return Ok(Some(message));
+            }
+        }
+        channel.blocked_receivers.push_back((job_id, now));
+        Ok(None)
+    }
+
+    // Hands the messages already available to the waiting receivers,
+    // oldest first. Returns each receiver with its message and the
+    // instant it started waiting.
+    pub fn deliver(&mut self, name: &str, now: SimTime) -> Result<Vec<(i32, Message, SimTime)>, ChannelError> {
+        let channel = self.channel(name)?;
+        let mut delivered = Vec::new();
+        while !channel.blocked_receivers.is_empty() {
+            let Some(message) = channel.ready_message(now) else {
+                break;
+            };
+            let (receiver, since) = channel.blocked_receivers.pop_front().unwrap();
+            delivered.push((receiver, message, since));
+        }
+        Ok(delivered)
+    }
+
+    // Moves the messages of waiting senders into the buffer while
+    // there is space, oldest first. Returns each sender with the
+    // ready time of its message and the instant it started waiting.
+    pub fn admit_senders(&mut self, name: &str, now: SimTime) -> Result<Vec<(i32, SimTime, SimTime)>, ChannelError> {
+        let channel = self.channel(name)?;
+        let mut admitted = Vec::new();
+        while let Some(&(sender, size, since)) = channel.blocked_senders.front() {
+            if !channel.fits(size) {
+                break;
+            }
+            channel.blocked_senders.pop_front();
+            channel.enqueue(sender, size, now);
+            admitted.push((sender, now + channel.latency, since));
+        }
+        Ok(admitted)
+    }
+
+    // Takes the job out of the waiting lists of the channel, as if it
+    // had never sent or received on it
+    pub fn cancel_wait(&mut self, name: &str, job_id: i32) {
+        if let Some(channel) = self.channels.get_mut(name) {
+            channel.blocked_senders.retain(|&(sender, _, _)| sender != job_id);
+            channel.blocked_receivers.retain(|&(receiver, _)| receiver != job_id);
+        }
+    }
+
+    // Drops the job from every waiting list. Messages it already sent
+    // stay in the buffers.
+    pub fn remove_job(&mut self, job_id: i32) {
+        for channel in self.channels.values_mut() {
+            channel.blocked_senders.retain(|&(sender, _, _)| sender != job_id);
+            channel.blocked_receivers.retain(|&(receiver, _)| receiver != job_id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn t(ticks: i32) -> SimTime {
+        SimTime::from(ticks)
+    }
+
+    #[test]
+    fn test_pipe_blocks_sender_until_space() {
+        let mut manager = ChannelManager::new();
+        manager.create_channel(String::from("p"), ChannelKind::Pipe, 10, SimDuration::from(2));
+
+        assert_eq!(manager.send("p", 1, 6, t(0)), Ok(Some(t(2))));
+        assert_eq!(manager.send("p", 1, 6, t(1)), Ok(None));
+        assert_eq!(manager.send("p", 1, 20, t(1)), Err(ChannelError::MessageTooLarge(String::from("p"), 20)));
+
+        // Not ready before the latency has passed
+        assert_eq!(manager.receive("p", 2, t(1)), Ok(None));
+        let delivered = manager.deliver("p", t(2)).unwrap();
+        assert_eq!(delivered.len(), 1);
+        assert_eq!((delivered[0].0, delivered[0].1.size, delivered[0].2), (2, 6, t(1)));
+
+        assert_eq!(manager.admit_senders("p", t(2)), Ok(vec![(1, t(4), t(1))]));
+        assert_eq!(manager.get("p").unwrap().buffered(), 1);
+    }
+
+    #[test]
+    fn test_message_queue_counts_messages() {
+        let mut manager = ChannelManager::new();
+        manager.create_channel(String::from("q"), ChannelKind::MessageQueue, 2, SimDuration::from(0));
+
+        assert_eq!(manager.send("q", 1, 100, t(0)), Ok(Some(t(0))));
+        assert_eq!(manager.send("q", 1, 100, t(0)), Ok(Some(t(0))));
+        assert_eq!(manager.send("q", 3, 1, t(0)), Ok(None));
+
+        // The waiting sender leaves with the job
+        manager.remove_job(3);
+        assert_eq!(manager.receive("q", 2, t(0)).unwrap().unwrap().sender, 1);
+        assert_eq!(manager.admit_senders("q", t(0)), Ok(vec![]));
+
+        // So does a sender whose wait is called off
+        assert_eq!(manager.send("q", 4, 1, t(0)), Ok(Some(t(0))));
+        assert_eq!(manager.send("q", 5, 1, t(0)), Ok(None));
+        manager.cancel_wait("q", 5);
+        assert_eq!(manager.receive("q", 2, t(0)).unwrap().unwrap().sender, 1);
+        assert_eq!(manager.admit_senders("q", t(0)), Ok(vec![]));
+        assert_eq!(manager.receive("nada", 2, t(0)), Err(ChannelError::UnknownChannel(String::from("nada"))));
+    }
+}
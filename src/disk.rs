@@ -0,0 +1,258 @@
+// In this file it is implemented the disk device: a single arm over
+// a range of cylinders serving one request at a time. Waiting
+// requests are picked by the disk scheduling policy, and each access
+// takes the seek time, proportional to the distance the arm moves,
+// plus the rotation and transfer times.
+use crate::time::{SimDuration, SimTime};
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum DiskSchedulingPolicy {
+    // Order of arrival
+    #[default]
+    Fcfs,
+    // Shortest seek time first: closest cylinder to the arm
+    Sstf,
+    // The arm sweeps to the end of the disk and back (elevator)
+    Scan,
+    // The arm sweeps up to the end and returns to cylinder 0
+    CScan,
+    // Like SCAN, but the arm turns back at the last request
+    Look,
+}
+
+impl fmt::Display for DiskSchedulingPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DiskSchedulingPolicy::Fcfs => write!(f, "FCFS"),
+            DiskSchedulingPolicy::Sstf => write!(f, "SSTF"),
+            DiskSchedulingPolicy::Scan => write!(f, "SCAN"),
+            DiskSchedulingPolicy::CScan => write!(f, "C-SCAN"),
+            DiskSchedulingPolicy::Look => write!(f, "LOOK"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DiskConfig {
+    pub policy: DiskSchedulingPolicy,
+    pub cylinders: i32,
+    // Cylinder the arm starts over
+    pub initial_cylinder: i32,
+    // Cylinders the arm crosses per timestep; taken as at least 1
+    pub seek_speed: i32,
+    // Average rotational latency, in timesteps
+    pub rotation_time: SimDuration,
+    pub transfer_time: SimDuration,
+}
+
+impl Default for DiskConfig {
+    fn default() -> Self {
+        DiskConfig {
+            policy: DiskSchedulingPolicy::Fcfs,
+            cylinders: 200,
+            initial_cylinder: 0,
+            seek_speed: 10,
+            rotation_time: SimDuration::from(3),
+            transfer_time: SimDuration::from(1),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DiskRequest {
+    pub job_id: i32,
+    pub cylinder: i32,
+    pub arrival: SimTime,
+}
+
+#[derive(Debug)]
+pub struct Disk {
+    config: DiskConfig,
+    head: i32,
+    // Whether the arm is moving towards higher cylinders
+    moving_up: bool,
+    queue: Vec<DiskRequest>,
+    // Request being served and the distance the arm moved for it
+    current: Option<(DiskRequest, i32)>,
+    // Set while the disk is serving a request or about to start one
+    busy: bool,
+}
+
+impl Default for Disk {
+    fn default() -> Self {
+        Self::new(DiskConfig::default())
+    }
+}
+
+impl Disk {
+    pub fn new(mut config: DiskConfig) -> Self {
+        config.seek_speed = config.seek_speed.max(1);
+        Disk { config, head: config.initial_cylinder, moving_up: true, queue: Vec::new(), current: None, busy: false }
+    }
+
+    pub fn policy(&self) -> DiskSchedulingPolicy {
+        self.config.policy
+    }
+
+    pub fn head(&self) -> i32 {
+        self.head
+    }
+
+    pub fn is_busy(&self) -> bool {
+        self.busy
+    }
+
+    pub fn has_requests(&self) -> bool {
+        !self.queue.is_empty()
+    }
+
+    // Queues a request. Returns true if the disk was idle, meaning
+    // the caller has to start the next access.
+    pub fn submit(&mut self, request: DiskRequest) -> bool {
+        self.queue.push(request);
+        let was_idle = !self.busy;
+        self.busy = true;
+        was_idle
+    }
+
+    // Drops the waiting requests of the job, returning how many there
+    // were. An access already in course is finished anyway.
+    pub fn cancel_requests(&mut self, job_id: i32) -> usize {
+        let waiting = self.queue.len();
+        self.queue.retain(|request| request.job_id != job_id);
+        waiting - self.queue.len()
+    }
+
+    // Picks the next request by the policy and moves the arm to it.
+    // Returns the request and the access time, or None (leaving the
+    // disk idle) if there is nothing to serve.
+    pub fn start_next(&mut self) -> Option<(DiskRequest, SimDuration)> {
+        let Some((position, distance)) = self.pick() else {
+            self.busy = false;
+            return None;
+        };
+        let request = self.queue.remove(position);
+        if request.cylinder != self.head {
+            self.moving_up = request.cylinder > self.head
+                || (self.config.policy == DiskSchedulingPolicy::CScan && request.cylinder < self.head);
+        }
+        self.head = request.cylinder;
+        self.current = Some((request, distance));
+        Some((request, self.access_time(distance)))
+    }
+
+    // Ends the access in course, returning the request served and
+    // the distance the arm moved for it
+    pub fn complete(&mut self) -> Option<(DiskRequest, i32)> {
+        self.current.take()
+    }
+
+    pub fn access_time(&self, distance: i32) -> SimDuration {
+        let seek = (distance + self.config.seek_speed - 1) / self.config.seek_speed;
+        SimDuration::from(seek) + self.config.rotation_time + self.config.transfer_time
+    }
+
+    // Position in the queue of the next request and the distance the
+    // arm travels to reach it
+    fn pick(&self) -> Option<(usize, i32)> {
+        if self.queue.is_empty() {
+            return None;
+        }
+        let head = self.head;
+        let last = self.config.cylinders - 1;
+        let closest = |candidates: &mut dyn Iterator<Item = (usize, &DiskRequest)>| {
+            candidates.min_by_key(|(_, request)| (request.cylinder - head).abs()).map(|(position, _)| position)
+        };
+        let ahead = |position: usize| {
+            let cylinder = self.queue[position].cylinder;
+            if self.moving_up { cylinder >= head } else { cylinder <= head }
+        };
+        let in_direction = closest(&mut self.queue.iter().enumerate().filter(|(position, _)| ahead(*position)));
+        let distance_to = |position: usize| (self.queue[position].cylinder - head).abs();
+
+        let picked = match self.config.policy {
+            DiskSchedulingPolicy::Fcfs => {
+                let position = 0;
+                (position, distance_to(position))
+            }
+            DiskSchedulingPolicy::Sstf => {
+                let position = closest(&mut self.queue.iter().enumerate()).unwrap();
+                (position, distance_to(position))
+            }
+            DiskSchedulingPolicy::Look => match in_direction {
+                Some(position) => (position, distance_to(position)),
+                None => {
+                    let position = closest(&mut self.queue.iter().enumerate()).unwrap();
+                    (position, distance_to(position))
+                }
+            },
+            DiskSchedulingPolicy::Scan => match in_direction {
+                Some(position) => (position, distance_to(position)),
+                None => {
+                    // Goes to the end of the disk, then turns back
+                    let end = if self.moving_up { last } else { 0 };
+                    let position = closest(&mut self.queue.iter().enumerate()).unwrap();
+                    (position, (end - head).abs() + (end - self.queue[position].cylinder).abs())
+                }
+            },
+            DiskSchedulingPolicy::CScan => {
+                let upwards = self.queue.iter().enumerate().filter(|(_, request)| request.cylinder >= head);
+                match upwards.min_by_key(|(_, request)| request.cylinder) {
+                    Some((position, _)) => (position, distance_to(position)),
+                    None => {
+                        // Goes to the end of the disk, returns to
+                        // cylinder 0 and sweeps up again
+                        let (position, request) =
+                            self.queue.iter().enumerate().min_by_key(|(_, request)| request.cylinder).unwrap();
+                        (position, (last - head) + last + request.cylinder)
+                    }
+                }
+            }
+        };
+        Some(picked)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Total arm movement serving the classic queue 98, 183, 37, 122,
+    // 14, 124, 65, 67 with the arm at cylinder 53
+    fn total_distance(policy: DiskSchedulingPolicy) -> (i32, Vec<i32>) {
+        let mut disk = Disk::new(DiskConfig { policy, initial_cylinder: 53, ..DiskConfig::default() });
+        for cylinder in [98, 183, 37, 122, 14, 124, 65, 67] {
+            disk.submit(DiskRequest { job_id: 1, cylinder, arrival: SimTime::ZERO });
+        }
+        let mut total = 0;
+        let mut order = Vec::new();
+        while disk.start_next().is_some() {
+            let (request, distance) = disk.complete().unwrap();
+            total += distance;
+            order.push(request.cylinder);
+        }
+        assert!(!disk.is_busy());
+        (total, order)
+    }
+
+    #[test]
+    fn test_seek_distance_per_policy() {
+        assert_eq!(total_distance(DiskSchedulingPolicy::Fcfs).0, 640);
+        assert_eq!(total_distance(DiskSchedulingPolicy::Sstf), (236, vec![65, 67, 37, 14, 98, 122, 124, 183]));
+        assert_eq!(total_distance(DiskSchedulingPolicy::Scan), (331, vec![65, 67, 98, 122, 124, 183, 37, 14]));
+        assert_eq!(total_distance(DiskSchedulingPolicy::CScan), (382, vec![65, 67, 98, 122, 124, 183, 14, 37]));
+        assert_eq!(total_distance(DiskSchedulingPolicy::Look), (299, vec![65, 67, 98, 122, 124, 183, 37, 14]));
+    }
+
+    #[test]
+    fn test_access_time() {
+        let disk = Disk::default();
+        assert_eq!(disk.access_time(0), SimDuration::from(4));
+        assert_eq!(disk.access_time(25), SimDuration::from(7));
+
+        // A disk whose arm would never move crosses one cylinder per timestep
+        let disk = Disk::new(DiskConfig { seek_speed: 0, ..DiskConfig::default() });
+        assert_eq!(disk.access_time(25), SimDuration::from(29));
+    }
+}
@@ -1,9 +1,19 @@
+use crate::job_profile::Burst;
 use crate::system_abstractions::{Job};
+use crate::time::{SimDuration, SimTime};
+use std::collections::BTreeMap;
 use std::fmt;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Metadata {
-    JobArrival(i32, i32, i32),
+    // Job, memory size and processing time
+    JobArrival(i32, i32, SimDuration),
+    // Job, memory size and the bursts the job goes through
+    ProfiledJobArrival(i32, i32, Vec<Burst>),
+    // Job, memory size, processing time and the source of its program
+    ProgramJobArrival(i32, i32, SimDuration, String),
+    // Job control language cards of a submitted job
+    JobDeck(String),
     JobEntrance(Job),
     RequestMemory(Job),
     RequestCPU(Job),
@@ -12,11 +22,100 @@ pub enum Metadata {
     FreeMemory(Job),
     ExitSystem(Job),
     PauseJob(Job),
+    // Job and the processing time it had since getting the processor
+    StartIo(Job, SimDuration),
+    EndIo(Job),
+    EndCardRead(Job),
+    EndPrint(Job),
+    KillJob(i32),
+    DeclareResources(i32, Vec<i32>),
+    RequestResources(i32, Vec<i32>),
+    ReleaseResources(i32, Vec<i32>),
+    SetPriority(i32, i32),
+    DetectDeadlock,
+    SemaphoreWait(i32, String),
+    SemaphoreSignal(i32, String),
+    WakeJob(i32, String),
+    SendMessage(i32, String, i32),
+    ReceiveMessage(i32, String),
+    DeliverMessage(String),
+    CreateDirectory(i32, String),
+    CreateFile(i32, String),
+    // Job, path and number of bytes appended
+    WriteFile(i32, String, i32),
+    // Job, path, offset and number of bytes
+    ReadFile(i32, String, i32, i32),
+    DeleteFile(i32, String),
+    // Job and cylinder
+    DiskRequest(i32, i32),
+    StartDiskAccess,
+    EndDiskAccess(i32),
+    // Job and logical address
+    MemoryAccess(i32, i32),
+    ProtectionFault(i32, i32),
+    // Event kind defined outside the crate: its kind and its fields,
+    // read by the routine registered for it
+    Custom(String, BTreeMap<String, String>),
     DefaultRoutine,
 }
 
+impl Metadata {
+    // Id of the job the event refers to, if any
+    pub fn job_id(&self) -> Option<i32> {
+        match self {
+            Metadata::JobArrival(id, _, _)
+            | Metadata::ProfiledJobArrival(id, _, _)
+            | Metadata::ProgramJobArrival(id, _, _, _)
+            | Metadata::KillJob(id)
+            | Metadata::DeclareResources(id, _)
+            | Metadata::RequestResources(id, _)
+            | Metadata::ReleaseResources(id, _)
+            | Metadata::SetPriority(id, _)
+            | Metadata::SemaphoreWait(id, _)
+            | Metadata::SemaphoreSignal(id, _)
+            | Metadata::WakeJob(id, _)
+            | Metadata::SendMessage(id, _, _)
+            | Metadata::ReceiveMessage(id, _)
+            | Metadata::CreateDirectory(id, _)
+            | Metadata::CreateFile(id, _)
+            | Metadata::WriteFile(id, _, _)
+            | Metadata::ReadFile(id, _, _, _)
+            | Metadata::DeleteFile(id, _)
+            | Metadata::DiskRequest(id, _)
+            | Metadata::EndDiskAccess(id)
+            | Metadata::MemoryAccess(id, _)
+            | Metadata::ProtectionFault(id, _) => Some(*id),
+            _ => self.job().map(|job| job.id),
+        }
+    }
+    // Job carried by the event, if any
+    pub fn job(&self) -> Option<&Job> {
+        match self {
+            Metadata::JobEntrance(job)
+            | Metadata::RequestMemory(job)
+            | Metadata::RequestCPU(job)
+            | Metadata::EndProcess(job)
+            | Metadata::FreeCPU(job)
+            | Metadata::FreeMemory(job)
+            | Metadata::ExitSystem(job)
+            | Metadata::PauseJob(job)
+            | Metadata::StartIo(job, _)
+            | Metadata::EndIo(job)
+            | Metadata::EndCardRead(job)
+            | Metadata::EndPrint(job) => Some(job),
+            _ => None,
+        }
+    }
+}
+
+// Identifies a scheduled event, so it can be cancelled or
+// rescheduled after being pushed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct EventHandle(u64);
+
 pub struct Event {
-    pub time: i32,
+    pub handle: EventHandle,
+    pub time: SimTime,
     pub name: String,
     pub metadata: Metadata,
     pub next: Option<Box<Event>>,
@@ -34,11 +133,12 @@ impl fmt::Debug for Event {
 
 pub struct EventList {
     head: Option<Box<Event>>,
+    next_handle: u64,
 }
 
 impl fmt::Debug for EventList {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "EventList: [\n")?;
+        writeln!(f, "EventList: [")?;
         if let Some(head) = &self.head {
             write!(f, "{:?}", head)?;
             let mut current = &head.next;
@@ -51,18 +151,36 @@ impl fmt::Debug for EventList {
     }
 }
 
+impl Default for EventList {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl EventList {
     pub fn new() -> Self {
-        EventList { head: None }
+        EventList { head: None, next_handle: 0 }
     }
 
-    pub fn push(&mut self, time: i32, name: String, metadata: Metadata) {
-        let mut new_event = Box::new(Event {
+    pub fn push(&mut self, time: impl Into<SimTime>, name: String, metadata: Metadata) -> EventHandle {
+        let time = time.into();
+        let handle = EventHandle(self.next_handle);
+        self.next_handle += 1;
+
+        let new_event = Box::new(Event {
+            handle,
             time,
             name,
             metadata,
             next: None, // Initialize next as None for the new event
         });
+        self.insert(new_event);
+        handle
+    }
+
+    // Inserts the event after every event with the same or an earlier time
+    fn insert(&mut self, mut new_event: Box<Event>) {
+        let time = new_event.time;
 
         // Check if the list is empty or if the new event should be inserted at the beginning
         if self.head.is_none() || time < self.head.as_ref().unwrap().time {
@@ -83,10 +201,23 @@ impl EventList {
         }
     }
 
+    // First event of the list, without removing it
+    pub fn peek(&self) -> Option<&Event> {
+        self.head.as_deref()
+    }
+
+    pub fn len(&self) -> usize {
+        self.iter().count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.head.is_none()
+    }
+
     // Get an iterator over the event list
-    pub fn iter(&self) -> EventListIter {
+    pub fn iter(&self) -> EventListIter<'_> {
         EventListIter {
-            current: self.head.as_ref().map(|event| &**event),
+            current: self.head.as_deref(),
         }
     }
 
@@ -104,6 +235,52 @@ impl EventList {
         new_event.next = self.head.take();
         self.head = Some(new_event);
     }
+
+    // Removes every event matching the predicate, keeping the order
+    // of the remaining ones. Returns the removed events in order.
+    pub fn remove_where<F: Fn(&Event) -> bool>(&mut self, predicate: F) -> Vec<Event> {
+        let mut removed = Vec::new();
+        let mut current = self.head.take();
+        let mut tail = &mut self.head;
+
+        while let Some(mut event) = current {
+            current = event.next.take();
+            if predicate(&event) {
+                removed.push(*event);
+            } else {
+                tail = &mut tail.insert(event).next;
+            }
+        }
+        removed
+    }
+
+    // Removes the event with the given handle, if it is still pending
+    pub fn remove(&mut self, handle: EventHandle) -> Option<Event> {
+        self.remove_where(|event| event.handle == handle).pop()
+    }
+
+    // Moves a pending event to a new time, keeping its handle. The
+    // event goes after the events already scheduled for that time.
+    // Returns false if the event is no longer in the list.
+    pub fn reschedule(&mut self, handle: EventHandle, time: impl Into<SimTime>) -> bool {
+        match self.remove(handle) {
+            Some(mut event) => {
+                event.time = time.into();
+                self.insert(Box::new(event));
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn get(&self, handle: EventHandle) -> Option<&Event> {
+        self.iter().find(|event| event.handle == handle)
+    }
+
+    // Every pending event that refers to the job, in order
+    pub fn events_for_job(&self, job_id: i32) -> Vec<&Event> {
+        self.iter().filter(|event| event.metadata.job_id() == Some(job_id)).collect()
+    }
 }
 
 // Define an iterator for the event list
@@ -115,9 +292,8 @@ impl<'a> Iterator for EventListIter<'a> {
     type Item = &'a Event; 
 
     fn next(&mut self) -> Option<Self::Item> {
-        self.current.map(|event| {
-            self.current = event.next.as_ref().map(|event| &**event);
-            event
+        self.current.inspect(|event| {
+            self.current = event.next.as_deref();
         })
     }
 }
@@ -132,7 +308,7 @@ mod tests {
         let mut event_list: EventList = EventList::new();
 
         // Push an event
-        event_list.push(999, String::from("Encerramento"), Metadata::JobArrival(1));
+        event_list.push(999, String::from("Encerramento"), Metadata::JobArrival(1, 0, SimDuration::from(0)));
 
         // Assert the event list has the correct length
         assert_eq!(event_list.iter().count(), 1);
@@ -144,8 +320,8 @@ mod tests {
         let mut event_list: EventList = EventList::new();
 
         // Push multiple events
-        event_list.push(999, String::from("Encerramento"), Metadata::JobArrival(1));
-        event_list.push(0, String::from("Partida"), Metadata::JobArrival(1));
+        event_list.push(999, String::from("Encerramento"), Metadata::JobArrival(1, 0, SimDuration::from(0)));
+        event_list.push(0, String::from("Partida"), Metadata::JobArrival(1, 0, SimDuration::from(0)));
 
         // Assert the event list has the correct length
         assert_eq!(event_list.iter().count(), 2);
@@ -167,8 +343,8 @@ mod tests {
     fn test_iter_multiple_events() {
         // Create an event list with events
         let mut event_list: EventList = EventList::new();
-        event_list.push(999, String::from("Encerramento"), Metadata::JobArrival(1));
-        event_list.push(0, String::from("Partida"), Metadata::JobArrival(1));
+        event_list.push(999, String::from("Encerramento"), Metadata::JobArrival(1, 0, SimDuration::from(0)));
+        event_list.push(0, String::from("Partida"), Metadata::JobArrival(1, 0, SimDuration::from(0)));
 
         // Iterate over the list and collect items
         let events: Vec<_> = event_list.iter().collect();
@@ -179,6 +355,61 @@ mod tests {
         assert_eq!(events[1].name, "Encerramento");
     }
 
+    #[test]
+    fn test_remove_where_keeps_order() {
+        let mut event_list: EventList = EventList::new();
+        event_list.push(30, String::from("Chegada de job"), Metadata::JobArrival(3, 0, SimDuration::from(0)));
+        event_list.push(10, String::from("Chegada de job"), Metadata::JobArrival(1, 0, SimDuration::from(0)));
+        event_list.push(20, String::from("Chegada de job"), Metadata::JobArrival(2, 0, SimDuration::from(0)));
+
+        let removed = event_list.remove_where(|event| event.metadata.job_id() == Some(2));
+
+        assert_eq!(removed.len(), 1);
+        assert_eq!(removed[0].time, SimTime::from(20));
+        let times: Vec<_> = event_list.iter().map(|event| event.time.ticks()).collect();
+        assert_eq!(times, vec![10, 30]);
+    }
+
+    #[test]
+    fn test_remove_by_handle() {
+        let mut event_list: EventList = EventList::new();
+        let first = event_list.push(10, String::from("Chegada de job"), Metadata::JobArrival(1, 0, SimDuration::from(0)));
+        let second = event_list.push(20, String::from("Chegada de job"), Metadata::JobArrival(2, 0, SimDuration::from(0)));
+
+        let removed = event_list.remove(first);
+
+        assert_eq!(removed.unwrap().handle, first);
+        assert!(event_list.remove(first).is_none());
+        assert_eq!(event_list.peek().unwrap().handle, second);
+        assert_eq!(event_list.len(), 1);
+    }
+
+    #[test]
+    fn test_reschedule_moves_event_after_same_time_events() {
+        let mut event_list: EventList = EventList::new();
+        let moved = event_list.push(10, String::from("Pause job"), Metadata::JobArrival(1, 0, SimDuration::from(0)));
+        event_list.push(30, String::from("Chegada de job"), Metadata::JobArrival(2, 0, SimDuration::from(0)));
+        event_list.push(50, String::from("Chegada de job"), Metadata::JobArrival(3, 0, SimDuration::from(0)));
+
+        assert!(event_list.reschedule(moved, 30));
+
+        let jobs: Vec<_> = event_list.iter().map(|event| event.metadata.job_id().unwrap()).collect();
+        assert_eq!(jobs, vec![2, 1, 3]);
+        assert_eq!(event_list.get(moved).unwrap().time, SimTime::from(30));
+    }
+
+    #[test]
+    fn test_events_for_job() {
+        let mut event_list: EventList = EventList::new();
+        event_list.push(10, String::from("Chegada de job"), Metadata::JobArrival(1, 0, SimDuration::from(0)));
+        event_list.push(20, String::from("Cancelamento de job"), Metadata::KillJob(2));
+        event_list.push(30, String::from("Cancelamento de job"), Metadata::KillJob(1));
+
+        let times: Vec<_> = event_list.events_for_job(1).iter().map(|event| event.time.ticks()).collect();
+        assert_eq!(times, vec![10, 30]);
+        assert!(event_list.events_for_job(3).is_empty());
+    }
+
     #[test]
     fn test_pop_empty_list() {
         // Create an empty event list
@@ -196,8 +427,8 @@ mod tests {
         // Create an event list with events
         let mut event_list: EventList = EventList::new();
 
-        event_list.push(999, String::from("Encerramento"), Metadata::JobArrival(1));
-        event_list.push(0, String::from("Partida"), Metadata::JobArrival(1));
+        event_list.push(999, String::from("Encerramento"), Metadata::JobArrival(1, 0, SimDuration::from(0)));
+        event_list.push(0, String::from("Partida"), Metadata::JobArrival(1, 0, SimDuration::from(0)));
         // Pop events from the list
         let popped_event1 = event_list.pop();
         let popped_event2 = event_list.pop();
@@ -1,14 +1,15 @@
-use crate::routines::{select_routine, create_routine};
+use crate::routines::select_routine;
+use crate::simulation_error::EventFailure;
 use crate::system_abstractions::ControlModule;
+use crate::time::SimTime;
 use std::collections::HashMap;
 
-pub fn event_loop (event_to_routine: &HashMap<&str, &str>, timestep: i32, control_module: &ControlModule) -> Option<i32> {
+pub fn event_loop (event_to_routine: &HashMap<&str, &str>, timestep: SimTime, control_module: &ControlModule) -> Result<Option<SimTime>, Box<EventFailure>> {
 
-    let mut continue_processing = true;
     let shared_state = &control_module.shared_state;
     let raw_event_list = shared_state.get_event_list();
 
-    while continue_processing {
+    loop {
         // Extract the first event of the list
         let event;
         {
@@ -16,8 +17,7 @@ pub fn event_loop (event_to_routine: &HashMap<&str, &str>, timestep: i32, contro
             if let Some(e) = event_list.pop() {
                 event = e;
             } else {
-                continue_processing = false;
-                continue;
+                break;
             }
         }
 
@@ -31,7 +31,7 @@ pub fn event_loop (event_to_routine: &HashMap<&str, &str>, timestep: i32, contro
                 let mut event_list = raw_event_list.lock().unwrap();
                 event_list.push_back(*event);
             }
-            return Some(time);
+            return Ok(Some(time));
         }
 
         // Generate event log
@@ -44,12 +44,28 @@ pub fn event_loop (event_to_routine: &HashMap<&str, &str>, timestep: i32, contro
         println!("Event metadata: {:?}", metadata);
         println!("\n");
 
+        control_module.record_trace(name.clone(), format!("{:?}", metadata));
+        control_module.record_event_statistics();
+
         // Select the function that will handle the event
-        let routine = select_routine(event_to_routine, &name);
-        let runnable = create_routine(&routine, metadata);
+        let routine = select_routine(event_to_routine, name);
+        let runnable = control_module.create_routine(routine, metadata);
+
+        control_module.notify(|observer| observer.before_event(time, &event));
 
-        // Execute the function
-        runnable.run(control_module);
+        // Execute the function, reporting the event and the
+        // system state if it fails
+        let result = runnable.run(control_module);
+        control_module.notify(|observer| observer.after_event(time, &event, result.as_ref().err()));
+        if let Err(error) = result {
+            return Err(Box::new(EventFailure {
+                time,
+                name: name.clone(),
+                metadata: metadata.clone(),
+                state: control_module.describe_state(),
+                error,
+            }));
         }
-    None
+    }
+    Ok(None)
 }
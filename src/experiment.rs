@@ -0,0 +1,243 @@
+// In this file it is implemented the experiment runner: the same
+// workload is simulated once for every combination of a grid of
+// parameters, and the statistics of each run are written as one row
+// of a CSV file. The workload is either one of the scenarios of
+// populate_list or a random one drawn from a seed.
+use std::io::{self, Write};
+
+use crate::clock::VirtualClock;
+use crate::event_list::{EventList, Metadata};
+use crate::populate_list::{populate_config, populate_list};
+use crate::routines::{create_event_to_routine, TIME_SLICE};
+use crate::system_abstractions::{ControlModule, MemoryAllocator, PlacementStrategy, SchedulerPolicy, SharedState, SystemConfig};
+use crate::time::SimDuration;
+
+// Small xorshift generator, so that random workloads only depend on
+// the seed
+#[derive(Debug, Clone)]
+pub struct Random {
+    state: u64,
+}
+
+impl Random {
+    pub fn new(seed: u64) -> Self {
+        // The state must never be zero
+        Random { state: seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1 }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        self.state
+    }
+
+    // Uniform value in [low, high]
+    pub fn range(&mut self, low: i32, high: i32) -> i32 {
+        low + (self.next_u64() % (high - low + 1) as u64) as i32
+    }
+}
+
+// Jobs arriving at random instants in [0, 200], with memory sizes in
+// [10, 60] and processing times in [5, 60]
+pub fn random_workload(seed: u64, jobs: i32) -> EventList {
+    let mut random = Random::new(seed);
+    let mut event_list = EventList::new();
+    event_list.push(999, String::from("Encerramento"), Metadata::JobArrival(0, 0, SimDuration::from(0)));
+    for id in 1..=jobs {
+        let arrival = random.range(0, 200);
+        let memory_size = random.range(10, 60);
+        let cpu_time = random.range(5, 60);
+        event_list.push(arrival, String::from("Chegada de job"), Metadata::JobArrival(id, memory_size, SimDuration::from(cpu_time)));
+    }
+    event_list
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Workload {
+    // One of the scenarios of populate_list, with its configuration
+    Scenario(i32),
+    // Random jobs drawn from the seed of each run
+    Random { jobs: i32 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ExperimentParameters {
+    pub scheduler: SchedulerPolicy,
+    pub memory_allocator: MemoryAllocator,
+    pub placement: PlacementStrategy,
+    pub memory_size: i32,
+    pub time_slice: SimDuration,
+    pub multiprogramming_level: i32,
+    pub seed: u64,
+}
+
+// Values of each parameter; every combination is run
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sweep {
+    pub schedulers: Vec<SchedulerPolicy>,
+    pub memory_allocators: Vec<MemoryAllocator>,
+    pub placements: Vec<PlacementStrategy>,
+    pub memory_sizes: Vec<i32>,
+    pub time_slices: Vec<SimDuration>,
+    pub multiprogramming_levels: Vec<i32>,
+    pub seeds: Vec<u64>,
+}
+
+impl Default for Sweep {
+    fn default() -> Self {
+        Sweep {
+            schedulers: vec![SchedulerPolicy::RoundRobin, SchedulerPolicy::Fcfs],
+            memory_allocators: vec![MemoryAllocator::VariablePartition, MemoryAllocator::Buddy],
+            placements: vec![PlacementStrategy::FirstFit, PlacementStrategy::BestFit, PlacementStrategy::WorstFit],
+            memory_sizes: vec![128, 256],
+            time_slices: vec![SimDuration::from(5), TIME_SLICE, SimDuration::from(20)],
+            multiprogramming_levels: vec![1, 2, 4],
+            seeds: vec![1],
+        }
+    }
+}
+
+impl Sweep {
+    // Only variable partitions use the placement strategy, so the other
+    // allocators are only run with the first one
+    pub fn runs(&self) -> Vec<ExperimentParameters> {
+        let mut runs = Vec::new();
+        for &scheduler in &self.schedulers {
+            for &memory_allocator in &self.memory_allocators {
+                let placements = match memory_allocator {
+                    MemoryAllocator::VariablePartition => &self.placements[..],
+                    MemoryAllocator::Buddy | MemoryAllocator::FixedPartitions(_) => {
+                        &self.placements[..self.placements.len().min(1)]
+                    }
+                };
+                for &placement in placements {
+                    for &memory_size in &self.memory_sizes {
+                        for &time_slice in &self.time_slices {
+                            for &multiprogramming_level in &self.multiprogramming_levels {
+                                for &seed in &self.seeds {
+                                    runs.push(ExperimentParameters {
+                                        scheduler,
+                                        memory_allocator,
+                                        placement,
+                                        memory_size,
+                                        time_slice,
+                                        multiprogramming_level,
+                                        seed,
+                                    });
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        runs
+    }
+}
+
+pub const CSV_HEADER: &str = "escalonador,alocador,posicionamento,memoria,fatia_de_tempo,multiprogramacao,semente,\
+status,eventos,instante_final,jobs_concluidos,jobs_abortados,turnaround_medio,utilizacao_memoria,fragmentacao_externa";
+
+fn run_experiment(workload: Workload, parameters: &ExperimentParameters) -> String {
+    let (event_list, base_config) = match workload {
+        Workload::Scenario(test_case) => (populate_list(test_case), populate_config(test_case)),
+        Workload::Random { jobs } => (random_workload(parameters.seed, jobs), SystemConfig::default()),
+    };
+    let config = SystemConfig {
+        scheduler: parameters.scheduler,
+        memory_allocator: parameters.memory_allocator,
+        placement: parameters.placement,
+        memory_size: parameters.memory_size,
+        time_slice: parameters.time_slice,
+        multiprogramming_level: parameters.multiprogramming_level,
+        ..base_config
+    };
+
+    let event_to_routine = create_event_to_routine();
+    let shared_state = SharedState::from_config(event_list, &config);
+    let mut control_module = ControlModule::with_clock(shared_state, Box::new(VirtualClock::new()));
+    let status = match control_module.run(&event_to_routine) {
+        Ok(_) => "ok",
+        Err(_) => "erro",
+    };
+
+    let statistics = control_module.shared_state.get_statistics();
+    let statistics = statistics.lock().unwrap();
+    let show = |value: Option<f64>| value.map_or(String::new(), |value| format!("{:.4}", value));
+    format!(
+        "{},{},{},{},{},{},{},{},{},{},{},{},{},{},{}",
+        parameters.scheduler,
+        parameters.memory_allocator,
+        parameters.placement,
+        parameters.memory_size,
+        parameters.time_slice,
+        parameters.multiprogramming_level,
+        parameters.seed,
+        status,
+        statistics.events_processed,
+        statistics.final_timestep,
+        statistics.jobs_completed(),
+        statistics.jobs_killed(),
+        statistics.mean_turnaround().map_or(String::new(), |turnaround| format!("{:.2}", turnaround)),
+        show(statistics.mean_memory_utilisation()),
+        show(statistics.mean_external_fragmentation()),
+    )
+}
+
+// Runs the workload once per combination of the sweep, writing the
+// CSV header and one row per run. A run that fails is still written,
+// with status "erro" and the statistics up to the failure.
+pub fn run_sweep<W: Write>(workload: Workload, sweep: &Sweep, output: &mut W) -> io::Result<()> {
+    writeln!(output, "{}", CSV_HEADER)?;
+    for parameters in sweep.runs() {
+        writeln!(output, "{}", run_experiment(workload, &parameters))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sweep_covers_every_combination() {
+        let sweep = Sweep { seeds: vec![1, 2], ..Sweep::default() };
+        let runs = sweep.runs();
+        // Three placements for variable partitions, one for the buddy
+        // allocator
+        assert_eq!(runs.len(), 2 * (3 + 1) * 2 * 3 * 3 * 2);
+        assert_eq!(runs[0].scheduler, SchedulerPolicy::RoundRobin);
+        assert_eq!(runs[1].seed, 2);
+
+        // The same seed draws the same workload
+        let draw = |seed| random_workload(seed, 5).iter().map(|event| (event.time, event.metadata.clone())).collect::<Vec<_>>();
+        assert_eq!(draw(7), draw(7));
+        assert_ne!(draw(7), draw(8));
+    }
+
+    #[test]
+    fn test_run_sweep_writes_one_row_per_run() {
+        let sweep = Sweep {
+            schedulers: vec![SchedulerPolicy::RoundRobin, SchedulerPolicy::Fcfs],
+            memory_allocators: vec![MemoryAllocator::VariablePartition],
+            placements: vec![PlacementStrategy::BestFit],
+            memory_sizes: vec![256],
+            time_slices: vec![SimDuration::from(10)],
+            multiprogramming_levels: vec![2],
+            seeds: vec![3],
+        };
+        let mut output = Vec::new();
+        run_sweep(Workload::Random { jobs: 4 }, &sweep, &mut output).unwrap();
+
+        let csv = String::from_utf8(output).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0], CSV_HEADER);
+        assert!(lines[1].starts_with("round-robin,variable-partition,best-fit,256,10,2,3,"));
+        assert!(lines[2].starts_with("fcfs,variable-partition,best-fit,256,10,2,3,"));
+        for line in &lines[1..] {
+            assert_eq!(line.split(',').count(), CSV_HEADER.split(',').count());
+        }
+    }
+}
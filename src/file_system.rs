@@ -0,0 +1,358 @@
+// In this file it is implemented the file system jobs use: a
+// directory tree whose files are stored in the blocks of a simulated
+// disk. Blocks are given to files by one of three strategies:
+// contiguous, linked or indexed allocation. Every operation reports
+// its access cost, the number of disk blocks it has to read or write.
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FileSystemError {
+    NotFound(String),
+    AlreadyExists(String),
+    // The directory the path should be created in does not exist
+    ParentNotFound(String),
+    // A directory that still has entries cannot be deleted
+    DirectoryNotEmpty(String),
+    // Not enough free blocks (or, for contiguous allocation, no free
+    // run long enough) for the write
+    DiskFull(String),
+    // The read goes past the end of the file
+    ReadPastEnd(String),
+    // A negative offset or number of bytes
+    InvalidRange(String),
+}
+
+impl fmt::Display for FileSystemError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FileSystemError::NotFound(path) => write!(f, "{} nao existe", path),
+            FileSystemError::AlreadyExists(path) => write!(f, "{} ja existe", path),
+            FileSystemError::ParentNotFound(path) => write!(f, "diretorio de {} nao existe", path),
+            FileSystemError::DirectoryNotEmpty(path) => write!(f, "diretorio {} nao esta vazio", path),
+            FileSystemError::DiskFull(path) => write!(f, "sem espaco em disco para {}", path),
+            FileSystemError::ReadPastEnd(path) => write!(f, "leitura alem do fim de {}", path),
+            FileSystemError::InvalidRange(path) => write!(f, "deslocamento ou tamanho negativo em {}", path),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum AllocationStrategy {
+    // The file occupies a run of consecutive blocks; growing past the
+    // run moves the whole file
+    #[default]
+    Contiguous,
+    // Each block points to the next one; reaching a block means
+    // reading every block before it
+    Linked,
+    // An index block lists the blocks of the file
+    Indexed,
+}
+
+impl fmt::Display for AllocationStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AllocationStrategy::Contiguous => write!(f, "contigua"),
+            AllocationStrategy::Linked => write!(f, "encadeada"),
+            AllocationStrategy::Indexed => write!(f, "indexada"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FileSystemConfig {
+    pub strategy: AllocationStrategy,
+    // Bytes per block
+    pub block_size: i32,
+    pub blocks: i32,
+}
+
+impl Default for FileSystemConfig {
+    fn default() -> Self {
+        FileSystemConfig { strategy: AllocationStrategy::Contiguous, block_size: 512, blocks: 64 }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct File {
+    pub size: i32,
+    pub blocks: Vec<usize>,
+    pub index_block: Option<usize>,
+}
+
+// Space wasted on the disk at some instant
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct FragmentationReport {
+    // Bytes allocated to files but not used by them
+    pub internal: i32,
+    // Share of the free blocks outside the largest free run
+    pub external: f64,
+    pub free_blocks: i32,
+}
+
+#[derive(Debug)]
+pub struct FileSystem {
+    strategy: AllocationStrategy,
+    block_size: i32,
+    // Whether each disk block is in use
+    used: Vec<bool>,
+    directories: BTreeSet<String>,
+    files: BTreeMap<String, File>,
+}
+
+impl Default for FileSystem {
+    fn default() -> Self {
+        Self::new(FileSystemConfig::default())
+    }
+}
+
+impl FileSystem {
+    pub fn new(config: FileSystemConfig) -> Self {
+        FileSystem {
+            strategy: config.strategy,
+            block_size: config.block_size,
+            used: vec![false; config.blocks as usize],
+            directories: BTreeSet::from([String::from("/")]),
+            files: BTreeMap::new(),
+        }
+    }
+
+    pub fn strategy(&self) -> AllocationStrategy {
+        self.strategy
+    }
+
+    pub fn file(&self, path: &str) -> Option<&File> {
+        self.files.get(path)
+    }
+
+    fn parent(path: &str) -> String {
+        match path.rfind('/') {
+            Some(0) | None => String::from("/"),
+            Some(position) => path[..position].to_string(),
+        }
+    }
+
+    fn check_new_path(&self, path: &str) -> Result<(), FileSystemError> {
+        if self.files.contains_key(path) || self.directories.contains(path) {
+            return Err(FileSystemError::AlreadyExists(path.to_string()));
+        }
+        if !self.directories.contains(&Self::parent(path)) {
+            return Err(FileSystemError::ParentNotFound(path.to_string()));
+        }
+        Ok(())
+    }
+
+    pub fn create_directory(&mut self, path: &str) -> Result<i32, FileSystemError> {
+        self.check_new_path(path)?;
+        self.directories.insert(path.to_string());
+        Ok(0)
+    }
+
+    // Creates an empty file. With indexed allocation the index block
+    // is written right away.
+    pub fn create(&mut self, path: &str) -> Result<i32, FileSystemError> {
+        self.check_new_path(path)?;
+        let mut file = File::default();
+        if self.strategy == AllocationStrategy::Indexed {
+            let block = self.free_blocks(1).ok_or_else(|| FileSystemError::DiskFull(path.to_string()))?;
+            self.mark(&block, true);
+            file.index_block = Some(block[0]);
+        }
+        let cost = file.index_block.map_or(0, |_| 1);
+        self.files.insert(path.to_string(), file);
+        Ok(cost)
+    }
+
+    // Appends bytes to the file, returning the access cost
+    pub fn write(&mut self, path: &str, bytes: i32) -> Result<i32, FileSystemError> {
+        let file = self.files.get(path).ok_or_else(|| FileSystemError::NotFound(path.to_string()))?.clone();
+        if bytes < 0 {
+            return Err(FileSystemError::InvalidRange(path.to_string()));
+        }
+        let size = file.size.checked_add(bytes).ok_or_else(|| FileSystemError::DiskFull(path.to_string()))?;
+        let needed = self.blocks_for(size) - file.blocks.len();
+        // The block the write starts in, if it is already allocated
+        let partial = usize::from(file.size % self.block_size != 0);
+
+        let (blocks, cost) = match self.strategy {
+            AllocationStrategy::Contiguous => {
+                let end = file.blocks.last().map_or(0, |last| last + 1);
+                let grows_in_place = !file.blocks.is_empty()
+                    && end + needed <= self.used.len()
+                    && self.used[end..end + needed].iter().all(|used| !used);
+                if needed == 0 || grows_in_place {
+                    let mut blocks = file.blocks.clone();
+                    blocks.extend(end..end + needed);
+                    (blocks, partial + needed)
+                } else {
+                    // Moves the file to a run long enough for all its
+                    // blocks: every old block is read and written again
+                    self.mark(&file.blocks, false);
+                    let total = file.blocks.len() + needed;
+                    match self.free_run(total) {
+                        Some(start) => ((start..start + total).collect(), 2 * file.blocks.len() + needed),
+                        None => {
+                            self.mark(&file.blocks, true);
+                            return Err(FileSystemError::DiskFull(path.to_string()));
+                        }
+                    }
+                }
+            }
+            AllocationStrategy::Linked => {
+                let new_blocks = self.free_blocks(needed).ok_or_else(|| FileSystemError::DiskFull(path.to_string()))?;
+                let mut blocks = file.blocks.clone();
+                blocks.extend(new_blocks);
+                // The list is followed up to its last block, which is
+                // written again with the new data or pointer
+                let tail = usize::from(!file.blocks.is_empty());
+                (blocks, file.blocks.len() + tail + needed)
+            }
+            AllocationStrategy::Indexed => {
+                let new_blocks = self.free_blocks(needed).ok_or_else(|| FileSystemError::DiskFull(path.to_string()))?;
+                let mut blocks = file.blocks.clone();
+                blocks.extend(new_blocks);
+                // The index block is read, and written again if the
+                // file got new blocks
+                (blocks, 1 + partial + needed + usize::from(needed > 0))
+            }
+        };
+
+        self.mark(&blocks, true);
+        let file = self.files.get_mut(path).unwrap();
+        file.blocks = blocks;
+        file.size = size;
+        Ok(cost as i32)
+    }
+
+    // Reads bytes from the given offset, returning the access cost
+    pub fn read(&self, path: &str, offset: i32, bytes: i32) -> Result<i32, FileSystemError> {
+        let file = self.files.get(path).ok_or_else(|| FileSystemError::NotFound(path.to_string()))?;
+        if offset < 0 || bytes < 0 {
+            return Err(FileSystemError::InvalidRange(path.to_string()));
+        }
+        if offset.checked_add(bytes).is_none_or(|end| end > file.size) {
+            return Err(FileSystemError::ReadPastEnd(path.to_string()));
+        }
+        if bytes == 0 {
+            return Ok(0);
+        }
+        let first = (offset / self.block_size) as usize;
+        let last = ((offset + bytes - 1) / self.block_size) as usize;
+        let cost = match self.strategy {
+            AllocationStrategy::Contiguous => last - first + 1,
+            // Every block up to the last one read is followed
+            AllocationStrategy::Linked => last + 1,
+            AllocationStrategy::Indexed => 1 + last - first + 1,
+        };
+        Ok(cost as i32)
+    }
+
+    pub fn delete(&mut self, path: &str) -> Result<i32, FileSystemError> {
+        if self.directories.contains(path) {
+            let prefix = format!("{}/", path);
+            let has_entries = self.files.keys().chain(self.directories.iter()).any(|entry| entry.starts_with(&prefix));
+            if has_entries || path == "/" {
+                return Err(FileSystemError::DirectoryNotEmpty(path.to_string()));
+            }
+            self.directories.remove(path);
+            return Ok(0);
+        }
+        let file = self.files.remove(path).ok_or_else(|| FileSystemError::NotFound(path.to_string()))?;
+        self.mark(&file.blocks, false);
+        if let Some(index_block) = file.index_block {
+            self.mark(&[index_block], false);
+        }
+        Ok(0)
+    }
+
+    pub fn fragmentation(&self) -> FragmentationReport {
+        let internal = self
+            .files
+            .values()
+            .map(|file| file.blocks.len() as i32 * self.block_size - file.size)
+            .sum();
+        let free_blocks = self.used.iter().filter(|used| !**used).count() as i32;
+        let largest_run = self
+            .used
+            .split(|used| *used)
+            .map(|run| run.len())
+            .max()
+            .unwrap_or(0) as i32;
+        let external = if free_blocks == 0 { 0.0 } else { (free_blocks - largest_run) as f64 / free_blocks as f64 };
+        FragmentationReport { internal, external, free_blocks }
+    }
+
+    fn blocks_for(&self, bytes: i32) -> usize {
+        (bytes as usize).div_ceil(self.block_size as usize)
+    }
+
+    fn mark(&mut self, blocks: &[usize], used: bool) {
+        for &block in blocks {
+            self.used[block] = used;
+        }
+    }
+
+    // The first free blocks, wherever they are
+    fn free_blocks(&self, count: usize) -> Option<Vec<usize>> {
+        let blocks: Vec<usize> = (0..self.used.len()).filter(|&block| !self.used[block]).take(count).collect();
+        (blocks.len() == count).then_some(blocks)
+    }
+
+    // Start of the first run of free blocks long enough (first fit)
+    fn free_run(&self, count: usize) -> Option<usize> {
+        (0..=self.used.len().saturating_sub(count)).find(|&start| self.used[start..start + count].iter().all(|used| !used))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file_system(strategy: AllocationStrategy) -> FileSystem {
+        FileSystem::new(FileSystemConfig { strategy, block_size: 100, blocks: 8 })
+    }
+
+    #[test]
+    fn test_contiguous_file_moves_when_it_cannot_grow() {
+        let mut fs = file_system(AllocationStrategy::Contiguous);
+        fs.create("/a").unwrap();
+        fs.create("/b").unwrap();
+        assert_eq!(fs.write("/a", 150), Ok(2));
+        assert_eq!(fs.write("/b", 100), Ok(1));
+
+        // Block 2 belongs to /b, so /a is copied to blocks 3 to 5
+        assert_eq!(fs.write("/a", 100), Ok(5));
+        assert_eq!(fs.file("/a").unwrap().blocks, vec![3, 4, 5]);
+
+        let report = fs.fragmentation();
+        assert_eq!(report.internal, 50);
+        assert_eq!(report.free_blocks, 4);
+        assert_eq!(report.external, 0.5);
+        assert_eq!(fs.write("/b", 300), Err(FileSystemError::DiskFull(String::from("/b"))));
+    }
+
+    #[test]
+    fn test_read_cost_per_strategy() {
+        for (strategy, cost) in [
+            (AllocationStrategy::Contiguous, 1),
+            (AllocationStrategy::Linked, 4),
+            (AllocationStrategy::Indexed, 2),
+        ] {
+            let mut fs = file_system(strategy);
+            fs.create_directory("/dados").unwrap();
+            fs.create("/dados/a").unwrap();
+            fs.write("/dados/a", 350).unwrap();
+            assert_eq!(fs.read("/dados/a", 300, 10), Ok(cost));
+            assert_eq!(fs.read("/dados/a", 300, 100), Err(FileSystemError::ReadPastEnd(String::from("/dados/a"))));
+            assert_eq!(fs.read("/dados/a", -1, 10), Err(FileSystemError::InvalidRange(String::from("/dados/a"))));
+            assert_eq!(fs.read("/dados/a", 0, i32::MAX), Err(FileSystemError::ReadPastEnd(String::from("/dados/a"))));
+            assert_eq!(fs.write("/dados/a", -10), Err(FileSystemError::InvalidRange(String::from("/dados/a"))));
+            assert_eq!(fs.delete("/dados"), Err(FileSystemError::DirectoryNotEmpty(String::from("/dados"))));
+        }
+        assert_eq!(
+            file_system(AllocationStrategy::Linked).create("/x/a"),
+            Err(FileSystemError::ParentNotFound(String::from("/x/a")))
+        );
+    }
+}
@@ -0,0 +1,136 @@
+// In this file it is implemented the parser of the job control
+// language jobs are submitted with. A deck starts with a $JOB card
+// and ends with a $END card; in between, control cards give the
+// attributes of the job:
+//
+//     $JOB 7
+//     $MEM 40
+//     $TIME 25
+//     $PRIORITY 2
+//     $END
+//
+// $MEM (memory size) and $TIME (processing time) are required and
+// $PRIORITY is optional. Cards not starting with $ are program and
+// data cards, which the monitor just carries along.
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum JclError {
+    // The deck does not start with a $JOB card
+    MissingJob,
+    // The deck ends without a $END card
+    MissingEnd,
+    // A required control card, such as $MEM, is missing
+    MissingCard(&'static str),
+    UnknownCard(String),
+    DuplicateCard(String),
+    // The card does not carry a valid number
+    InvalidValue(String),
+    // Cards after $END
+    TrailingCards,
+}
+
+impl fmt::Display for JclError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JclError::MissingJob => write!(f, "deck nao comeca com $JOB"),
+            JclError::MissingEnd => write!(f, "deck nao termina com $END"),
+            JclError::MissingCard(card) => write!(f, "deck sem o cartao {}", card),
+            JclError::UnknownCard(card) => write!(f, "cartao de controle desconhecido: {}", card),
+            JclError::DuplicateCard(card) => write!(f, "cartao {} repetido", card),
+            JclError::InvalidValue(card) => write!(f, "valor invalido no cartao: {}", card),
+            JclError::TrailingCards => write!(f, "cartoes apos $END"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct JobDeck {
+    pub id: i32,
+    pub memory_size: i32,
+    pub cpu_time: i32,
+    pub priority: Option<i32>,
+}
+
+// Value of a card such as "$MEM 40"; only priorities may be zero or
+// negative
+fn card_value(card: &str, fields: &[&str], positive: bool) -> Result<i32, JclError> {
+    match fields {
+        [value] => match value.parse::<i32>() {
+            Ok(value) if value > 0 || !positive => Ok(value),
+            _ => Err(JclError::InvalidValue(card.to_string())),
+        },
+        _ => Err(JclError::InvalidValue(card.to_string())),
+    }
+}
+
+pub fn parse_deck(deck: &str) -> Result<JobDeck, JclError> {
+    let mut cards = deck.lines().map(str::trim).filter(|card| !card.is_empty());
+
+    let first = cards.next().ok_or(JclError::MissingJob)?;
+    let fields: Vec<&str> = first.split_whitespace().collect();
+    if fields[0] != "$JOB" {
+        return Err(JclError::MissingJob);
+    }
+    let id = card_value(first, &fields[1..], true)?;
+
+    let (mut memory_size, mut cpu_time, mut priority) = (None, None, None);
+    let mut ended = false;
+    for card in cards.by_ref() {
+        if !card.starts_with('$') {
+            continue;
+        }
+        let fields: Vec<&str> = card.split_whitespace().collect();
+        let slot = match fields[0] {
+            "$END" => {
+                ended = true;
+                break;
+            }
+            "$MEM" => &mut memory_size,
+            "$TIME" => &mut cpu_time,
+            "$PRIORITY" => &mut priority,
+            "$JOB" => return Err(JclError::DuplicateCard(card.to_string())),
+            _ => return Err(JclError::UnknownCard(card.to_string())),
+        };
+        if slot.is_some() {
+            return Err(JclError::DuplicateCard(card.to_string()));
+        }
+        *slot = Some(card_value(card, &fields[1..], fields[0] != "$PRIORITY")?);
+    }
+    if !ended {
+        return Err(JclError::MissingEnd);
+    }
+    if cards.next().is_some() {
+        return Err(JclError::TrailingCards);
+    }
+
+    Ok(JobDeck {
+        id,
+        memory_size: memory_size.ok_or(JclError::MissingCard("$MEM"))?,
+        cpu_time: cpu_time.ok_or(JclError::MissingCard("$TIME"))?,
+        priority,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_deck() {
+        let deck = "$JOB 7\n$MEM 40\n  $TIME 25\nPROGRAMA\n$PRIORITY -1\n$END\n";
+        assert_eq!(parse_deck(deck), Ok(JobDeck { id: 7, memory_size: 40, cpu_time: 25, priority: Some(-1) }));
+        assert_eq!(parse_deck("$JOB 1\n$TIME 5\n$MEM 8\n$END").unwrap().priority, None);
+    }
+
+    #[test]
+    fn test_malformed_decks_are_rejected() {
+        assert_eq!(parse_deck("$MEM 40\n$END"), Err(JclError::MissingJob));
+        assert_eq!(parse_deck("$JOB 1\n$MEM 40\n$TIME 5"), Err(JclError::MissingEnd));
+        assert_eq!(parse_deck("$JOB 1\n$TIME 5\n$END"), Err(JclError::MissingCard("$MEM")));
+        assert_eq!(parse_deck("$JOB 1\n$MEM 0\n$END"), Err(JclError::InvalidValue(String::from("$MEM 0"))));
+        assert_eq!(parse_deck("$JOB 1\n$MEM 4\n$MEM 4\n$END"), Err(JclError::DuplicateCard(String::from("$MEM 4"))));
+        assert_eq!(parse_deck("$JOB 1\n$DISK 4\n$END"), Err(JclError::UnknownCard(String::from("$DISK 4"))));
+        assert_eq!(parse_deck("$JOB 1\n$MEM 4\n$TIME 2\n$END\n$JOB 2"), Err(JclError::TrailingCards));
+    }
+}
@@ -0,0 +1,99 @@
+// In this file it is implemented the behaviour profile of a job: the
+// sequence of bursts it goes through, alternating processing on the
+// cpu with input/output on some device. During an I/O burst the job
+// leaves the processor and waits for the device.
+use crate::time::SimDuration;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Burst {
+    // Processing time
+    Cpu(i32),
+    // Device name and time the device takes
+    Io(String, i32),
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct JobProfile {
+    bursts: Vec<Burst>,
+    // I/O bursts already started
+    ios_done: usize,
+}
+
+impl JobProfile {
+    pub fn new(bursts: Vec<Burst>) -> Self {
+        JobProfile { bursts, ios_done: 0 }
+    }
+
+    pub fn bursts(&self) -> &[Burst] {
+        &self.bursts
+    }
+
+    // Total processing time of the job
+    pub fn cpu_time(&self) -> SimDuration {
+        self.bursts
+            .iter()
+            .map(|burst| match burst {
+                Burst::Cpu(time) => SimDuration::from(*time),
+                Burst::Io(_, _) => SimDuration::ZERO,
+            })
+            .sum()
+    }
+
+    // Given the processing time the job has already had, returns the
+    // processing time left before its next I/O burst, with the device
+    // and the I/O time. An I/O burst after the last cpu burst is not
+    // performed, since the job ends with its processing.
+    pub fn next_io(&self, cpu_consumed: SimDuration) -> Option<(SimDuration, String, SimDuration)> {
+        let total = self.cpu_time();
+        let mut cpu_before = SimDuration::ZERO;
+        let mut io_index = 0;
+        for burst in &self.bursts {
+            match burst {
+                Burst::Cpu(time) => cpu_before += SimDuration::from(*time),
+                Burst::Io(device, time) => {
+                    if io_index >= self.ios_done && cpu_before < total {
+                        let cpu_until = (cpu_before - cpu_consumed).max(SimDuration::ZERO);
+                        return Some((cpu_until, device.clone(), SimDuration::from(*time)));
+                    }
+                    io_index += 1;
+                }
+            }
+        }
+        None
+    }
+
+    pub fn start_io(&mut self) {
+        self.ios_done += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn d(ticks: i32) -> SimDuration {
+        SimDuration::from(ticks)
+    }
+
+    #[test]
+    fn test_next_io_follows_the_bursts() {
+        let mut profile = JobProfile::new(vec![
+            Burst::Cpu(15),
+            Burst::Io(String::from("Fita"), 20),
+            Burst::Cpu(5),
+            Burst::Io(String::from("Disco"), 8),
+            Burst::Cpu(10),
+            Burst::Io(String::from("Impressora"), 30),
+        ]);
+        assert_eq!(profile.cpu_time(), d(30));
+
+        assert_eq!(profile.next_io(d(0)), Some((d(15), String::from("Fita"), d(20))));
+        assert_eq!(profile.next_io(d(10)), Some((d(5), String::from("Fita"), d(20))));
+        profile.start_io();
+        assert_eq!(profile.next_io(d(15)), Some((d(5), String::from("Disco"), d(8))));
+        profile.start_io();
+
+        // The last I/O burst comes after all the processing
+        assert_eq!(profile.next_io(d(20)), None);
+    }
+}
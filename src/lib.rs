@@ -1,5 +1,26 @@
+// The crate name and the queue helpers (add_SEQ, remove_CAQ, ...)
+// follow the course naming, not snake case.
+#![allow(non_snake_case)]
+
+pub mod clock;
+pub mod communication;
+pub mod disk;
 pub mod event_list;
 pub mod event_loop;
+pub mod experiment;
+pub mod file_system;
+pub mod jcl;
+pub mod job_profile;
+pub mod machine;
+pub mod observer;
 pub mod routines;
 pub mod populate_list;
+pub mod protection;
+pub mod resources;
+pub mod simulation_error;
+pub mod spooling;
+pub mod statistics;
+pub mod synchronization;
 pub mod system_abstractions;
+pub mod time;
+pub mod trace;
@@ -0,0 +1,475 @@
+// In this file it is implemented the machine the jobs run on: a few
+// registers and a small instruction set. A program is assembled into
+// an image of words, one per instruction or datum, and the loader
+// copies the image to the start of the segment of its job. While the
+// job holds the processor the machine executes one instruction per
+// timestep, so how the jobs are scheduled decides when each of them
+// produces its output.
+//
+//     LI    R0, 5       ; R0 = 5
+//     ADD   R0, R1, R2  ; R0 = R1 + R2
+//     SUB   R0, R1, R2  ; R0 = R1 - R2
+//     LOAD  R0, total   ; R0 = memory[total]
+//     STORE R0, total   ; memory[total] = R0
+//     JMP   loop        ; go to loop
+//     JZ    R0, end     ; go to end if R0 is zero
+//     SYS   1           ; system call: 0 ends the program, 1 writes R0
+//     WORD  7           ; datum
+//
+// The assembler counts addresses from 0. The loader relocates them by
+// adding the start of the segment, so the machine runs on physical
+// addresses, still checked against the base and limit registers.
+use crate::protection::{BaseLimit, ProtectionFault};
+use crate::system_abstractions::Memory;
+use crate::time::SimDuration;
+use std::collections::HashMap;
+use std::fmt;
+
+pub const REGISTERS: usize = 4;
+
+// System calls
+pub const SYS_EXIT: i32 = 0;
+pub const SYS_WRITE: i32 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Instruction {
+    LoadImmediate(usize, i32),
+    Add(usize, usize, usize),
+    Sub(usize, usize, usize),
+    Load(usize, i32),
+    Store(usize, i32),
+    Jump(i32),
+    JumpIfZero(usize, i32),
+    Syscall(i32),
+}
+
+// A word holds the operation in its highest byte, a register in the
+// next one and an operand of 16 bits, with sign, in the lowest ones
+impl Instruction {
+    pub fn encode(&self) -> i32 {
+        let (operation, register, operand) = match *self {
+            Instruction::LoadImmediate(register, value) => (1, register, value),
+            Instruction::Add(target, left, right) => (2, target, (left << 4 | right) as i32),
+            Instruction::Sub(target, left, right) => (3, target, (left << 4 | right) as i32),
+            Instruction::Load(register, address) => (4, register, address),
+            Instruction::Store(register, address) => (5, register, address),
+            Instruction::Jump(address) => (6, 0, address),
+            Instruction::JumpIfZero(register, address) => (7, register, address),
+            Instruction::Syscall(call) => (8, 0, call),
+        };
+        operation << 24 | (register as i32) << 16 | (operand & 0xFFFF)
+    }
+
+    pub fn decode(word: i32) -> Option<Instruction> {
+        let register = ((word >> 16) & 0xFF) as usize;
+        let operand = word as i16 as i32;
+        let (left, right) = ((operand >> 4 & 0xF) as usize, (operand & 0xF) as usize);
+        if register >= REGISTERS {
+            return None;
+        }
+        let instruction = match word >> 24 {
+            1 => Instruction::LoadImmediate(register, operand),
+            2 => Instruction::Add(register, left, right),
+            3 => Instruction::Sub(register, left, right),
+            4 => Instruction::Load(register, operand),
+            5 => Instruction::Store(register, operand),
+            6 => Instruction::Jump(operand),
+            7 => Instruction::JumpIfZero(register, operand),
+            8 => Instruction::Syscall(operand),
+            _ => return None,
+        };
+        match instruction {
+            Instruction::Add(_, left, right) | Instruction::Sub(_, left, right) if left >= REGISTERS || right >= REGISTERS => None,
+            instruction => Some(instruction),
+        }
+    }
+
+    // The same instruction with the base added to its address field
+    pub fn relocate(&self, base: i32) -> Instruction {
+        match *self {
+            Instruction::Load(register, address) => Instruction::Load(register, address + base),
+            Instruction::Store(register, address) => Instruction::Store(register, address + base),
+            Instruction::Jump(address) => Instruction::Jump(address + base),
+            Instruction::JumpIfZero(register, address) => Instruction::JumpIfZero(register, address + base),
+            instruction => instruction,
+        }
+    }
+
+    fn has_address(&self) -> bool {
+        matches!(
+            self,
+            Instruction::Load(..) | Instruction::Store(..) | Instruction::Jump(..) | Instruction::JumpIfZero(..)
+        )
+    }
+}
+
+// Program as the assembler leaves it: its words, with addresses
+// counted from 0, and the positions of the words whose address field
+// the loader relocates. Data words are never relocated, even when they
+// look like an instruction.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Image {
+    pub words: Vec<i32>,
+    pub relocations: Vec<usize>,
+}
+
+impl Image {
+    pub fn len(&self) -> usize {
+        self.words.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.words.is_empty()
+    }
+
+    // Words to be placed from the base address on
+    pub fn relocate(&self, base: i32) -> Vec<i32> {
+        let mut words = self.words.clone();
+        for &position in &self.relocations {
+            if let Some(instruction) = Instruction::decode(words[position]) {
+                words[position] = instruction.relocate(base).encode();
+            }
+        }
+        words
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AssemblyError {
+    UnknownInstruction(String),
+    // Wrong number of operands, a bad register or a number that does
+    // not fit in 16 bits
+    InvalidOperand(String),
+    UnknownLabel(String),
+    DuplicateLabel(String),
+}
+
+impl fmt::Display for AssemblyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AssemblyError::UnknownInstruction(line) => write!(f, "instrucao desconhecida: {}", line),
+            AssemblyError::InvalidOperand(line) => write!(f, "operando invalido: {}", line),
+            AssemblyError::UnknownLabel(label) => write!(f, "rotulo {} nao definido", label),
+            AssemblyError::DuplicateLabel(label) => write!(f, "rotulo {} repetido", label),
+        }
+    }
+}
+
+// Words of the program; a line may start with "label:" and anything
+// after ";" is a comment
+pub fn assemble(source: &str) -> Result<Image, AssemblyError> {
+    // First pass: the address of every label
+    let mut lines = Vec::new();
+    let mut labels = HashMap::new();
+    for line in source.lines() {
+        let mut text = line.split(';').next().unwrap_or("").trim();
+        if let Some((label, rest)) = text.split_once(':') {
+            let label = label.trim();
+            if labels.insert(label.to_string(), lines.len() as i32).is_some() {
+                return Err(AssemblyError::DuplicateLabel(label.to_string()));
+            }
+            text = rest.trim();
+        }
+        if !text.is_empty() {
+            lines.push(text);
+        }
+    }
+
+    // Second pass: one word per line
+    let mut image = Image::default();
+    for line in lines {
+        let invalid = || AssemblyError::InvalidOperand(line.to_string());
+        let (mnemonic, operands) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let operands: Vec<&str> = operands.split(',').map(str::trim).filter(|operand| !operand.is_empty()).collect();
+        let register = |operand: &str| {
+            operand
+                .strip_prefix('R')
+                .and_then(|number| number.parse::<usize>().ok())
+                .filter(|&number| number < REGISTERS)
+                .ok_or_else(invalid)
+        };
+        let value = |operand: &str| match operand.parse::<i32>() {
+            Ok(number) if i16::try_from(number).is_ok() => Ok(number),
+            Ok(_) => Err(invalid()),
+            Err(_) => labels.get(operand).copied().ok_or_else(|| AssemblyError::UnknownLabel(operand.to_string())),
+        };
+        let instruction = match (mnemonic.to_uppercase().as_str(), operands.as_slice()) {
+            ("WORD", [datum]) => {
+                image.words.push(datum.parse::<i32>().map_err(|_| invalid())?);
+                continue;
+            }
+            ("LI", [target, number]) => Instruction::LoadImmediate(register(target)?, value(number)?),
+            ("ADD", [target, left, right]) => Instruction::Add(register(target)?, register(left)?, register(right)?),
+            ("SUB", [target, left, right]) => Instruction::Sub(register(target)?, register(left)?, register(right)?),
+            ("LOAD", [target, address]) => Instruction::Load(register(target)?, value(address)?),
+            ("STORE", [source, address]) => Instruction::Store(register(source)?, value(address)?),
+            ("JMP", [address]) => Instruction::Jump(value(address)?),
+            ("JZ", [tested, address]) => Instruction::JumpIfZero(register(tested)?, value(address)?),
+            ("SYS", [call]) => Instruction::Syscall(value(call)?),
+            ("WORD" | "LI" | "ADD" | "SUB" | "LOAD" | "STORE" | "JMP" | "JZ" | "SYS", _) => return Err(invalid()),
+            _ => return Err(AssemblyError::UnknownInstruction(line.to_string())),
+        };
+        if instruction.has_address() {
+            image.relocations.push(image.words.len());
+        }
+        image.words.push(instruction.encode());
+    }
+    Ok(image)
+}
+
+// Why the machine gave the processor back
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Stop {
+    // The time it was given ran out
+    Quantum,
+    // The program made the exit system call
+    Exited,
+    // The program used an address outside its segment
+    Fault(ProtectionFault),
+    // The word at the address is not an instruction
+    IllegalInstruction(i32),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Execution {
+    // Time the instructions took, one timestep each
+    pub elapsed: SimDuration,
+    pub stop: Stop,
+    // Loads and stores, the one that faulted included
+    pub memory_accesses: i32,
+    // Values written by the program, with the time since the start
+    // of the execution at which each one was written
+    pub output: Vec<(SimDuration, i32)>,
+}
+
+// Words the machine reads and writes, by physical address
+pub trait Storage {
+    fn read(&self, address: i32) -> Option<i32>;
+    fn write(&mut self, address: i32, value: i32);
+}
+
+impl Storage for Memory {
+    fn read(&self, address: i32) -> Option<i32> {
+        Memory::read(self, address)
+    }
+
+    fn write(&mut self, address: i32, value: i32) {
+        Memory::write(self, address, value)
+    }
+}
+
+// Copy of the words of one segment, at their physical addresses. A
+// program run on it can only change the copy, and it is all the
+// program can reach past the protection checks.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SegmentCopy {
+    base: i32,
+    words: Vec<i32>,
+}
+
+impl SegmentCopy {
+    pub fn new(memory: &Memory, registers: BaseLimit) -> Self {
+        let words = (registers.base..registers.base + registers.limit).map(|address| memory.read(address).unwrap_or(0)).collect();
+        SegmentCopy { base: registers.base, words }
+    }
+}
+
+impl Storage for SegmentCopy {
+    fn read(&self, address: i32) -> Option<i32> {
+        self.words.get(usize::try_from(address - self.base).ok()?).copied()
+    }
+
+    fn write(&mut self, address: i32, value: i32) {
+        if let Some(word) = usize::try_from(address - self.base).ok().and_then(|offset| self.words.get_mut(offset)) {
+            *word = value;
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Machine {
+    pub registers: [i32; REGISTERS],
+    // Physical address of the next instruction
+    pub program_counter: i32,
+    // Program the loader copies to memory
+    image: Image,
+    pub exited: bool,
+}
+
+impl Machine {
+    // Machine for a program that will be loaded in a segment of the
+    // given size; None if it does not fit
+    pub fn new(image: Image, segment_size: i32) -> Option<Self> {
+        if image.len() > usize::try_from(segment_size).ok()? {
+            return None;
+        }
+        Some(Machine { registers: [0; REGISTERS], program_counter: 0, image, exited: false })
+    }
+
+    pub fn image(&self) -> &Image {
+        &self.image
+    }
+
+    // The program starts at the address it was loaded at
+    pub fn start(&mut self, base: i32) {
+        self.program_counter = base;
+    }
+
+    // Executes instructions from memory until the time runs out or the
+    // program stops. Every address is checked against the base and
+    // limit registers.
+    pub fn run(&mut self, time: SimDuration, registers: BaseLimit, memory: &mut impl Storage) -> Execution {
+        let mut elapsed = SimDuration::ZERO;
+        let mut memory_accesses = 0;
+        let mut output = Vec::new();
+        let stop = loop {
+            if self.exited {
+                break Stop::Exited;
+            }
+            // A word that cannot be fetched stops the program at once,
+            // even when the time has just run out
+            let address = self.program_counter;
+            if let Err(fault) = registers.check(address) {
+                break Stop::Fault(fault);
+            }
+            let Some(instruction) = memory.read(address).and_then(Instruction::decode) else {
+                break Stop::IllegalInstruction(address);
+            };
+            if elapsed >= time {
+                break Stop::Quantum;
+            }
+            elapsed += SimDuration::from(1);
+            self.program_counter += 1;
+            if let Instruction::Load(..) | Instruction::Store(..) = instruction {
+                memory_accesses += 1;
+            }
+            match instruction {
+                Instruction::LoadImmediate(target, value) => self.registers[target] = value,
+                Instruction::Add(target, left, right) => {
+                    self.registers[target] = self.registers[left].wrapping_add(self.registers[right])
+                }
+                Instruction::Sub(target, left, right) => {
+                    self.registers[target] = self.registers[left].wrapping_sub(self.registers[right])
+                }
+                Instruction::Load(target, address) => match registers.check(address) {
+                    Ok(address) => self.registers[target] = memory.read(address).unwrap_or(0),
+                    Err(fault) => break Stop::Fault(fault),
+                },
+                Instruction::Store(source, address) => match registers.check(address) {
+                    Ok(address) => memory.write(address, self.registers[source]),
+                    Err(fault) => break Stop::Fault(fault),
+                },
+                Instruction::Jump(address) => self.program_counter = address,
+                Instruction::JumpIfZero(tested, address) => {
+                    if self.registers[tested] == 0 {
+                        self.program_counter = address;
+                    }
+                }
+                Instruction::Syscall(SYS_EXIT) => self.exited = true,
+                Instruction::Syscall(SYS_WRITE) => output.push((elapsed, self.registers[0])),
+                Instruction::Syscall(_) => break Stop::IllegalInstruction(address),
+            }
+        };
+        Execution { elapsed, stop, memory_accesses, output }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Writes 3, 2 and 1, then ends
+    const COUNTDOWN: &str = "
+              LOAD  R0, start
+              LI    R1, 1
+        loop: JZ    R0, end
+              SYS   1
+              SUB   R0, R0, R1
+              JMP   loop
+        end:  SYS   0
+        start: WORD 3
+    ";
+
+    #[test]
+    fn test_instructions_round_trip() {
+        for instruction in [
+            Instruction::LoadImmediate(3, -5),
+            Instruction::Add(0, 1, 2),
+            Instruction::Store(1, 300),
+            Instruction::JumpIfZero(2, 7),
+            Instruction::Syscall(SYS_WRITE),
+        ] {
+            assert_eq!(Instruction::decode(instruction.encode()), Some(instruction));
+        }
+        assert_eq!(Instruction::decode(0), None);
+        assert_eq!(assemble("LI R4, 1"), Err(AssemblyError::InvalidOperand(String::from("LI R4, 1"))));
+        assert_eq!(assemble("JMP nowhere"), Err(AssemblyError::UnknownLabel(String::from("nowhere"))));
+        assert_eq!(assemble("HALT"), Err(AssemblyError::UnknownInstruction(String::from("HALT"))));
+    }
+
+    // Relocates and copies the program as the loader does
+    fn load(image: Image, registers: BaseLimit, memory: &mut Memory) -> Machine {
+        memory.load(registers.base, &image.relocate(registers.base));
+        let mut machine = Machine::new(image, registers.limit).unwrap();
+        machine.start(registers.base);
+        machine
+    }
+
+    #[test]
+    fn test_program_runs_across_quanta() {
+        let program = assemble(COUNTDOWN).unwrap();
+        assert_eq!(program.len(), 8);
+        assert_eq!(program.relocations, vec![0, 2, 5]);
+        let registers = BaseLimit { base: 100, limit: 10 };
+        let mut memory = Memory::new(128);
+        let mut machine = load(program.clone(), registers, &mut memory);
+
+        // The address fields now point into the segment; the datum is
+        // copied as it is
+        assert_eq!(memory.read(100).and_then(Instruction::decode), Some(Instruction::Load(0, 107)));
+        assert_eq!(memory.read(105).and_then(Instruction::decode), Some(Instruction::Jump(102)));
+        assert_eq!(memory.read(107), Some(3));
+
+        let first = machine.run(SimDuration::from(5), registers, &mut memory);
+        assert_eq!(first.stop, Stop::Quantum);
+        assert_eq!(first.output, vec![(SimDuration::from(4), 3)]);
+
+        let rest = machine.run(SimDuration::from(100), registers, &mut memory);
+        assert_eq!(rest.stop, Stop::Exited);
+        let values: Vec<i32> = rest.output.iter().map(|&(_, value)| value).collect();
+        assert_eq!(values, vec![2, 1]);
+        assert_eq!(first.elapsed + rest.elapsed, SimDuration::from(16));
+
+        // A store past the limit stops the program
+        let mut machine = load(assemble("LI R0, 1\nSTORE R0, 10").unwrap(), registers, &mut memory);
+        let execution = machine.run(SimDuration::from(10), registers, &mut memory);
+        assert_eq!(execution.stop, Stop::Fault(ProtectionFault { address: 10, limit: 10 }));
+        assert_eq!(memory.read(110), Some(0));
+        assert!(Machine::new(program, 4).is_none());
+    }
+
+    #[test]
+    fn test_segment_copy_runs_like_memory() {
+        let source = "
+                   LOAD  R0, value
+                   STORE R0, copy
+                   SYS   1
+                   SYS   0
+            value: WORD  9
+            copy:  WORD  0
+        ";
+        let registers = BaseLimit { base: 40, limit: 8 };
+        let mut memory = Memory::new(128);
+        let machine = load(assemble(source).unwrap(), registers, &mut memory);
+        let mut segment = SegmentCopy::new(&memory, registers);
+
+        // The copy stops where the memory does, and only the copy has
+        // the word the program stored
+        let ahead = machine.clone().run(SimDuration::from(10), registers, &mut segment);
+        assert_eq!(segment.read(45), Some(9));
+        assert_eq!(memory.read(45), Some(0));
+        assert_eq!(segment.read(48), None);
+        assert_eq!(machine.clone().run(SimDuration::from(10), registers, &mut memory), ahead);
+        assert_eq!(ahead.stop, Stop::Exited);
+    }
+}
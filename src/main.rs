@@ -1,78 +1,94 @@
-use std::time::{Duration, Instant};
-use std::thread::sleep;
-use std::collections::HashMap;
+#![allow(non_snake_case)]
+
+use std::fs::File;
+use std::io::BufWriter;
+use std::time::Duration;
+use PCS3446::clock::PacedClock;
+use PCS3446::experiment::{run_sweep, Sweep, Workload};
 use PCS3446::routines::create_event_to_routine;
-use PCS3446::event_loop::event_loop;
-use PCS3446::populate_list::populate_list;
-use PCS3446::system_abstractions::{Memory, ControlModule, SharedState, SystemEntryQueue, ExecQueue, MemoryAllocQueue, CPUAllocQueue, JobTable};
+use PCS3446::populate_list::{populate_config, populate_list};
+use PCS3446::system_abstractions::{ControlModule, SharedState};
+
+fn usage() -> ! {
+    eprintln!("uso: PCS3446 [ms_por_timestep] [--memoria <arquivo.csv>] | PCS3446 sweep <arquivo.csv> [cenario]");
+    std::process::exit(2);
+}
+
+// Batch mode: `PCS3446 sweep <arquivo.csv> [cenario]` runs the
+// scenario (or a random workload, when none is given) across the
+// default parameter grid and writes the statistics to the file
+fn sweep(mut args: impl Iterator<Item = String>) {
+    let Some(path) = args.next() else {
+        eprintln!("uso: PCS3446 sweep <arquivo.csv> [cenario]");
+        std::process::exit(2);
+    };
+    let workload = match args.next().map(|scenario| scenario.parse::<i32>()) {
+        Some(Ok(test_case)) => Workload::Scenario(test_case),
+        Some(Err(_)) => {
+            eprintln!("cenario invalido");
+            std::process::exit(2);
+        }
+        None => Workload::Random { jobs: 10 },
+    };
+
+    let result = File::create(&path).and_then(|file| run_sweep(workload, &Sweep::default(), &mut BufWriter::new(file)));
+    if let Err(error) = result {
+        eprintln!("{}: {}", path, error);
+        std::process::exit(1);
+    }
+}
 
+// Default mode: `PCS3446 [ms_por_timestep] [--memoria <arquivo.csv>]`
+// simulates scenario 2, each timestep lasting the given real time
+// (1000 ms if none is given), and optionally writes the memory usage
+// samples of the run to the file
 fn main() {
+    let mut args = std::env::args().skip(1).peekable();
+    if args.peek().map(String::as_str) == Some("sweep") {
+        args.next();
+        sweep(args);
+        return;
+    }
+
     // Define the number of timesteps and the time delay in milliseconds
     let num_timesteps = 1000;
-    let timestep_duration_ms = 1000;
-
-    // Initialize the current timestep and a start time
-    let mut current_timestep = 0;
-    let start_time = Instant::now();
+    let mut timestep_duration_ms = 1000;
+    let mut memory_series = None;
+    while let Some(arg) = args.next() {
+        if arg == "--memoria" {
+            memory_series = Some(args.next().unwrap_or_else(|| usage()));
+        } else {
+            timestep_duration_ms = arg.parse::<u64>().unwrap_or_else(|_| usage());
+        }
+    }
 
     // Build event_to_routine hashmap
     let event_to_routine = create_event_to_routine();
 
-    // Build event list
-    // let mut event_list: PCS3446::event_list::EventList<Option<i32>> = EventList::new();
-
     // Populate event list
-    let event_list = populate_list(2);
+    let test_case = 2;
+    let event_list = populate_list(test_case);
 
     // Create the control module and its requirements
-    let system_entry_queue = SystemEntryQueue::new();
-    let memory_alloc_queue = MemoryAllocQueue::new();
-    let cpu_alloc_queue = CPUAllocQueue::new();
-    let exec_queue = ExecQueue::new();
-    let memory = Memory::new(128);
-    let job_table = JobTable::new();
-
-    let shared_state = SharedState::new(event_list, system_entry_queue, memory_alloc_queue, cpu_alloc_queue, exec_queue, memory, current_timestep, job_table);
+    let shared_state = SharedState::from_config(event_list, &populate_config(test_case));
 
-    let mut control_module = ControlModule::new(shared_state);
+    // The simulation is paced against the host clock
+    let clock = PacedClock::new(Duration::from_millis(timestep_duration_ms), num_timesteps);
+    let mut control_module = ControlModule::with_clock(shared_state, Box::new(clock));
 
     // Enter the event loop
-    while current_timestep < num_timesteps {
-        // Perform actions for the current timestep
-        let new_timestep = process_current_timestep(&event_to_routine, current_timestep, &control_module);
-
-        // Calculate the elapsed time since the start
-        let elapsed_time = start_time.elapsed();
-
-        // Calculate the desired duration for the current timestep
-        let desired_duration = Duration::from_millis(timestep_duration_ms as u64);
-
-        // Calculate the remaining time to sleep to maintain the desired timestep duration
-        let remaining_time = if elapsed_time < desired_duration {
-            desired_duration - elapsed_time
-        } else {
-            Duration::from_millis(0)
-        };
-
-        // Sleep to maintain the desired timestep duration
-        sleep(remaining_time);
-
-        // Increment the current timestep
-        current_timestep = new_timestep;
-        control_module.update_current_timestep(current_timestep);
+    if let Err(failure) = control_module.run(&event_to_routine) {
+        eprintln!("{}", failure);
+        std::process::exit(1);
     }
-}
 
-fn process_current_timestep(event_to_routine: &HashMap<&str, &str>, timestep: i32, control_module: &ControlModule) -> i32 {
-    // Your code for processing the current timestep goes here
-    println!("Instante de simulacao: {}", timestep);
-    // println!("Event list: {:?}", event_list);
-    //println!("Hashmap: {:?}", event_to_routine);
-
-    if let Some(new_timestep) = event_loop(event_to_routine, timestep, control_module) {
-        new_timestep
-    } else {
-        timestep+1
+    if let Some(path) = memory_series {
+        let statistics = control_module.shared_state.get_statistics();
+        let statistics = statistics.lock().unwrap();
+        let result = File::create(&path).and_then(|file| statistics.write_memory_series(&mut BufWriter::new(file)));
+        if let Err(error) = result {
+            eprintln!("{}: {}", path, error);
+            std::process::exit(1);
+        }
     }
 }
-
@@ -0,0 +1,107 @@
+// In this file it is implemented the observers of the simulation.
+// An observer registered on the control module is told about every
+// event handled, every change of job state, every job entering or
+// leaving a queue and every memory allocation, so statistics,
+// visualisers and custom checks can follow the simulation without
+// patching the routines. Every method does nothing by default.
+use crate::event_list::Event;
+use crate::simulation_error::SimulationError;
+use crate::system_abstractions::Segment;
+use crate::time::SimTime;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum QueueChange {
+    Added,
+    Removed,
+}
+
+pub trait Observer {
+    fn before_event(&mut self, _time: SimTime, _event: &Event) {}
+
+    // Called after the routine of the event ran, with its error if
+    // it failed
+    fn after_event(&mut self, _time: SimTime, _event: &Event, _error: Option<&SimulationError>) {}
+
+    fn job_state_changed(&mut self, _time: SimTime, _job_id: i32, _old_state: i32, _new_state: i32) {}
+
+    // The queue is named as in remove_from_queues, e.g. "ExecQueue"
+    fn queue_changed(&mut self, _time: SimTime, _queue: &'static str, _change: QueueChange, _job_id: i32) {}
+
+    fn memory_allocated(&mut self, _time: SimTime, _job_id: i32, _segment: &Segment) {}
+
+    fn memory_freed(&mut self, _time: SimTime, _job_id: i32, _size: i32) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::populate_list::{populate_config, populate_list};
+    use crate::routines::create_event_to_routine;
+    use crate::system_abstractions::{ControlModule, SharedState};
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Default)]
+    struct Counts {
+        events: i32,
+        unfinished_events: i32,
+        jobs_done: i32,
+        allocated: i32,
+        freed: i32,
+        exec_queue_length: i32,
+    }
+
+    struct Recorder(Arc<Mutex<Counts>>);
+
+    impl Observer for Recorder {
+        fn before_event(&mut self, _time: SimTime, _event: &Event) {
+            self.0.lock().unwrap().unfinished_events += 1;
+        }
+
+        fn after_event(&mut self, _time: SimTime, _event: &Event, error: Option<&SimulationError>) {
+            assert!(error.is_none());
+            let mut counts = self.0.lock().unwrap();
+            counts.unfinished_events -= 1;
+            counts.events += 1;
+        }
+
+        fn job_state_changed(&mut self, _time: SimTime, _job_id: i32, _old_state: i32, new_state: i32) {
+            if new_state == 6 {
+                self.0.lock().unwrap().jobs_done += 1;
+            }
+        }
+
+        fn queue_changed(&mut self, _time: SimTime, queue: &'static str, change: QueueChange, _job_id: i32) {
+            if queue == "ExecQueue" {
+                let mut counts = self.0.lock().unwrap();
+                counts.exec_queue_length += if change == QueueChange::Added { 1 } else { -1 };
+                assert!(counts.exec_queue_length >= 0);
+            }
+        }
+
+        fn memory_allocated(&mut self, _time: SimTime, _job_id: i32, segment: &Segment) {
+            self.0.lock().unwrap().allocated += segment.size();
+        }
+
+        fn memory_freed(&mut self, _time: SimTime, _job_id: i32, size: i32) {
+            self.0.lock().unwrap().freed += size;
+        }
+    }
+
+    #[test]
+    fn test_observer_follows_the_simulation() {
+        let shared_state = SharedState::from_config(populate_list(2), &populate_config(2));
+        let mut control_module = ControlModule::new(shared_state);
+        let counts = Arc::new(Mutex::new(Counts::default()));
+        control_module.add_observer(Box::new(Recorder(counts.clone())));
+        control_module.run(&create_event_to_routine()).unwrap();
+
+        let counts = counts.lock().unwrap();
+        let statistics = control_module.shared_state.get_statistics();
+        let statistics = statistics.lock().unwrap();
+        assert_eq!(counts.events, statistics.events_processed);
+        assert_eq!(counts.unfinished_events, 0);
+        assert_eq!(counts.jobs_done, statistics.jobs_completed());
+        assert!(counts.allocated > 0);
+        assert_eq!(counts.allocated, counts.freed);
+    }
+}
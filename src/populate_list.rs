@@ -2,35 +2,405 @@
 // test cases for the event list. Thus, it contains
 // functions that populate the event list before the
 // trigger of the event loop in different cases.
+use crate::communication::ChannelKind;
+use crate::disk::{DiskConfig, DiskSchedulingPolicy};
 use crate::event_list::*;
+use crate::file_system::{AllocationStrategy, FileSystemConfig};
+use crate::job_profile::Burst;
+use crate::resources::{RecoveryStrategy, ResourcePolicy};
+use crate::spooling::SpoolingConfig;
+use crate::system_abstractions::{MemoryAllocator, PartitionQueues, SystemConfig};
+use crate::time::SimDuration;
 
 pub fn populate_list(test_case: i32) -> EventList{
     match test_case {
         1 => test_1(),
-        2 => test_2(),
+        2 | 18 | 20..=22 => test_2(),
+        3 => test_3(),
+        4 => test_4(),
+        5 | 6 => test_5(),
+        7 => test_7(),
+        8 => test_8(),
+        9..=11 => test_9(),
+        12..=16 => test_12(),
+        17 => test_17(),
+        19 => test_19(),
+        23 => test_23(),
+        24 => test_24(),
         _ => EventList::new(),
     }
 }
 
+// System parameters each test case runs with
+pub fn populate_config(test_case: i32) -> SystemConfig {
+    match test_case {
+        4 => SystemConfig { resources: vec![(String::from("Fita"), 3)], ..SystemConfig::default() },
+        5 => SystemConfig {
+            resources: vec![(String::from("Fita"), 1), (String::from("Impressora"), 1)],
+            resource_policy: ResourcePolicy::detection(SimDuration::from(20), RecoveryStrategy::KillLowestPriority).unwrap(),
+            ..SystemConfig::default()
+        },
+        6 => SystemConfig {
+            resources: vec![(String::from("Fita"), 1), (String::from("Impressora"), 1)],
+            resource_policy: ResourcePolicy::detection(SimDuration::from(20), RecoveryStrategy::PreemptResources).unwrap(),
+            ..SystemConfig::default()
+        },
+        7 => SystemConfig {
+            semaphores: vec![(String::from("vazio"), 1), (String::from("cheio"), 0)],
+            mutexes: vec![String::from("buffer")],
+            ..SystemConfig::default()
+        },
+        8 => SystemConfig {
+            channels: vec![
+                (String::from("dados"), ChannelKind::Pipe, 64, SimDuration::from(3)),
+                (String::from("confirmacoes"), ChannelKind::MessageQueue, 1, SimDuration::from(1)),
+            ],
+            ..SystemConfig::default()
+        },
+        9 => file_system_config(AllocationStrategy::Contiguous),
+        10 => file_system_config(AllocationStrategy::Linked),
+        11 => file_system_config(AllocationStrategy::Indexed),
+        12 => disk_config(DiskSchedulingPolicy::Fcfs),
+        13 => disk_config(DiskSchedulingPolicy::Sstf),
+        14 => disk_config(DiskSchedulingPolicy::Scan),
+        15 => disk_config(DiskSchedulingPolicy::CScan),
+        16 => disk_config(DiskSchedulingPolicy::Look),
+        // Arrivals of test_2 going through the card reader and printer
+        18 => SystemConfig {
+            spooling: Some(SpoolingConfig {
+                card_time: SimDuration::from(1),
+                line_time: SimDuration::from(2),
+                default_cards: 8,
+                default_lines: 6,
+                decks: vec![(1, 15, 20), (4, 4, 3)],
+            }),
+            ..SystemConfig::default()
+        },
+        // Arrivals of test_2 in a memory managed by the buddy system
+        20 => SystemConfig { memory_allocator: MemoryAllocator::Buddy, ..SystemConfig::default() },
+        21 => partition_config(PartitionQueues::Single),
+        22 => partition_config(PartitionQueues::PerPartition),
+        _ => SystemConfig::default(),
+    }
+}
+
+// Disk of 200 cylinders with the arm starting at cylinder 53, used
+// by the disk scheduling test cases
+fn disk_config(policy: DiskSchedulingPolicy) -> SystemConfig {
+    SystemConfig {
+        disk: DiskConfig { policy, initial_cylinder: 53, ..DiskConfig::default() },
+        ..SystemConfig::default()
+    }
+}
+
+// Fixed partitions of 30k, 40k, 60k and 80k holding up to four jobs,
+// used by the MFT test cases
+fn partition_config(queues: PartitionQueues) -> SystemConfig {
+    SystemConfig {
+        memory_allocator: MemoryAllocator::FixedPartitions(queues),
+        partitions: vec![30, 40, 60, 80],
+        multiprogramming_level: 4,
+        ..SystemConfig::default()
+    }
+}
+
+// Disk of 16 blocks of 512 bytes, used by the file system test cases
+fn file_system_config(strategy: AllocationStrategy) -> SystemConfig {
+    SystemConfig {
+        file_system: FileSystemConfig { strategy, block_size: 512, blocks: 16 },
+        ..SystemConfig::default()
+    }
+}
+
+// Every named test case, used by the golden trace tests.
+pub const SCENARIOS: &[(&str, i32)] = &[
+    ("test_1", 1),
+    ("test_2", 2),
+    ("test_3", 3),
+    ("test_4", 4),
+    ("test_5", 5),
+    ("test_6", 6),
+    ("test_7", 7),
+    ("test_8", 8),
+    ("test_9", 9),
+    ("test_10", 10),
+    ("test_11", 11),
+    ("test_12", 12),
+    ("test_13", 13),
+    ("test_14", 14),
+    ("test_15", 15),
+    ("test_16", 16),
+    ("test_17", 17),
+    ("test_18", 18),
+    ("test_19", 19),
+    ("test_20", 20),
+    ("test_21", 21),
+    ("test_22", 22),
+    ("test_23", 23),
+    ("test_24", 24),
+];
+
 pub fn test_1() -> EventList {
     let mut event_list: EventList = EventList::new();
-    event_list.push(999, String::from("Encerramento"), Metadata::JobArrival(0, 0, 0));
-    event_list.push(240, String::from("Chegada de job"), Metadata::JobArrival(4, 40, 40));
-    event_list.push(220, String::from("Chegada de job"), Metadata::JobArrival(3, 80, 80));
-    event_list.push(20, String::from("Chegada de job"), Metadata::JobArrival(1, 30, 60));
-    event_list.push(20, String::from("Chegada de job"), Metadata::JobArrival(2, 100, 120));
+    event_list.push(999, String::from("Encerramento"), Metadata::JobArrival(0, 0, SimDuration::from(0)));
+    event_list.push(240, String::from("Chegada de job"), Metadata::JobArrival(4, 40, SimDuration::from(40)));
+    event_list.push(220, String::from("Chegada de job"), Metadata::JobArrival(3, 80, SimDuration::from(80)));
+    event_list.push(20, String::from("Chegada de job"), Metadata::JobArrival(1, 30, SimDuration::from(60)));
+    event_list.push(20, String::from("Chegada de job"), Metadata::JobArrival(2, 100, SimDuration::from(120)));
 
     event_list
 }
 
 pub fn test_2() -> EventList {
     let mut event_list: EventList = EventList::new();
-    event_list.push(999, String::from("Encerramento"), Metadata::JobArrival(0, 0, 0));
-    event_list.push(50, String::from("Chegada de job"), Metadata::JobArrival(5, 80, 20));
-    event_list.push(40, String::from("Chegada de job"), Metadata::JobArrival(4, 50, 15));
-    event_list.push(30, String::from("Chegada de job"), Metadata::JobArrival(3, 30, 10));
-    event_list.push(20, String::from("Chegada de job"), Metadata::JobArrival(2, 60, 12));
-    event_list.push(10, String::from("Chegada de job"), Metadata::JobArrival(1, 40, 25));
+    event_list.push(999, String::from("Encerramento"), Metadata::JobArrival(0, 0, SimDuration::from(0)));
+    event_list.push(50, String::from("Chegada de job"), Metadata::JobArrival(5, 80, SimDuration::from(20)));
+    event_list.push(40, String::from("Chegada de job"), Metadata::JobArrival(4, 50, SimDuration::from(15)));
+    event_list.push(30, String::from("Chegada de job"), Metadata::JobArrival(3, 30, SimDuration::from(10)));
+    event_list.push(20, String::from("Chegada de job"), Metadata::JobArrival(2, 60, SimDuration::from(12)));
+    event_list.push(10, String::from("Chegada de job"), Metadata::JobArrival(1, 40, SimDuration::from(25)));
+
+    event_list
+}
+
+// Same arrivals as test_2, with one job cancelled while running and
+// another cancelled before it arrives.
+pub fn test_3() -> EventList {
+    let mut event_list = test_2();
+    event_list.push(25, String::from("Cancelamento de job"), Metadata::KillJob(2));
+    event_list.push(45, String::from("Cancelamento de job"), Metadata::KillJob(5));
+
+    event_list
+}
+
+// Two jobs sharing three tape drives. Job 1 asks for its second
+// drive while job 2 holds one: granting it would leave a single free
+// drive with both jobs still needing more, an unsafe state, so job 1
+// is blocked until job 2 gives its drives back.
+pub fn test_4() -> EventList {
+    let mut event_list: EventList = EventList::new();
+    event_list.push(999, String::from("Encerramento"), Metadata::JobArrival(0, 0, SimDuration::from(0)));
+    event_list.push(10, String::from("Chegada de job"), Metadata::JobArrival(1, 20, SimDuration::from(40)));
+    event_list.push(10, String::from("Declaracao de recursos de job"), Metadata::DeclareResources(1, vec![3]));
+    event_list.push(12, String::from("Chegada de job"), Metadata::JobArrival(2, 20, SimDuration::from(30)));
+    event_list.push(12, String::from("Declaracao de recursos de job"), Metadata::DeclareResources(2, vec![2]));
+    event_list.push(15, String::from("Requisicao de recursos de job"), Metadata::RequestResources(1, vec![1]));
+    event_list.push(18, String::from("Requisicao de recursos de job"), Metadata::RequestResources(2, vec![1]));
+    event_list.push(25, String::from("Requisicao de recursos de job"), Metadata::RequestResources(1, vec![1]));
+    event_list.push(30, String::from("Requisicao de recursos de job"), Metadata::RequestResources(2, vec![1]));
+    event_list.push(40, String::from("Liberacao de recursos de job"), Metadata::ReleaseResources(2, vec![2]));
+
+    event_list
+}
+
+// Two jobs each holding the resource the other one asks for. With
+// deadlock detection (test cases 5 and 6) the check at instant 40
+// finds the circular wait and picks job 2, which has the lowest
+// priority, as the victim.
+pub fn test_5() -> EventList {
+    let mut event_list: EventList = EventList::new();
+    event_list.push(999, String::from("Encerramento"), Metadata::JobArrival(0, 0, SimDuration::from(0)));
+    event_list.push(10, String::from("Chegada de job"), Metadata::JobArrival(1, 20, SimDuration::from(60)));
+    event_list.push(10, String::from("Definicao de prioridade de job"), Metadata::SetPriority(1, 1));
+    event_list.push(10, String::from("Declaracao de recursos de job"), Metadata::DeclareResources(1, vec![1, 1]));
+    event_list.push(12, String::from("Chegada de job"), Metadata::JobArrival(2, 20, SimDuration::from(60)));
+    event_list.push(12, String::from("Declaracao de recursos de job"), Metadata::DeclareResources(2, vec![1, 1]));
+    event_list.push(15, String::from("Requisicao de recursos de job"), Metadata::RequestResources(1, vec![1, 0]));
+    event_list.push(18, String::from("Requisicao de recursos de job"), Metadata::RequestResources(2, vec![0, 1]));
+    event_list.push(25, String::from("Requisicao de recursos de job"), Metadata::RequestResources(1, vec![0, 1]));
+    event_list.push(28, String::from("Requisicao de recursos de job"), Metadata::RequestResources(2, vec![1, 0]));
+    event_list.push(60, String::from("Liberacao de recursos de job"), Metadata::ReleaseResources(1, vec![1, 1]));
+
+    event_list
+}
+
+// Producer (job 1) and consumer (job 2) sharing a one-slot buffer,
+// guarded by the semaphores "vazio" and "cheio" and the mutex
+// "buffer". The consumer blocks twice waiting for an item.
+pub fn test_7() -> EventList {
+    let mut event_list: EventList = EventList::new();
+    event_list.push(999, String::from("Encerramento"), Metadata::JobArrival(0, 0, SimDuration::from(0)));
+    event_list.push(10, String::from("Chegada de job"), Metadata::JobArrival(1, 20, SimDuration::from(60)));
+    event_list.push(12, String::from("Chegada de job"), Metadata::JobArrival(2, 20, SimDuration::from(60)));
+
+    let operations = [
+        (20, "Wait em semaforo de job", 1, "vazio"),
+        (21, "Wait em semaforo de job", 1, "buffer"),
+        (22, "Wait em semaforo de job", 2, "cheio"),
+        (24, "Signal em semaforo de job", 1, "buffer"),
+        (25, "Signal em semaforo de job", 1, "cheio"),
+        (30, "Wait em semaforo de job", 2, "buffer"),
+        (33, "Signal em semaforo de job", 2, "buffer"),
+        (34, "Signal em semaforo de job", 2, "vazio"),
+        (40, "Wait em semaforo de job", 1, "vazio"),
+        (41, "Wait em semaforo de job", 1, "buffer"),
+        (42, "Wait em semaforo de job", 2, "cheio"),
+        (45, "Signal em semaforo de job", 1, "buffer"),
+        (46, "Signal em semaforo de job", 1, "cheio"),
+    ];
+    for (time, name, job, object) in operations {
+        let metadata = if name.starts_with("Wait") {
+            Metadata::SemaphoreWait(job, String::from(object))
+        } else {
+            Metadata::SemaphoreSignal(job, String::from(object))
+        };
+        event_list.push(time, String::from(name), metadata);
+    }
+
+    event_list
+}
+
+// Pipeline of two jobs: job 1 writes blocks of 40 bytes into the
+// pipe "dados" (64 bytes, latency 3), so its second write waits for
+// job 2 to read the first one; job 2 acknowledges through the
+// message queue "confirmacoes".
+pub fn test_8() -> EventList {
+    let mut event_list: EventList = EventList::new();
+    event_list.push(999, String::from("Encerramento"), Metadata::JobArrival(0, 0, SimDuration::from(0)));
+    event_list.push(10, String::from("Chegada de job"), Metadata::JobArrival(1, 20, SimDuration::from(40)));
+    event_list.push(12, String::from("Chegada de job"), Metadata::JobArrival(2, 20, SimDuration::from(40)));
+    event_list.push(22, String::from("Recepcao de mensagem de job"), Metadata::ReceiveMessage(2, String::from("dados")));
+    event_list.push(24, String::from("Envio de mensagem de job"), Metadata::SendMessage(1, String::from("dados"), 40));
+    event_list.push(25, String::from("Envio de mensagem de job"), Metadata::SendMessage(1, String::from("dados"), 40));
+    event_list.push(32, String::from("Recepcao de mensagem de job"), Metadata::ReceiveMessage(2, String::from("dados")));
+    event_list.push(35, String::from("Envio de mensagem de job"), Metadata::SendMessage(2, String::from("confirmacoes"), 1));
+    event_list.push(36, String::from("Recepcao de mensagem de job"), Metadata::ReceiveMessage(1, String::from("confirmacoes")));
+
+    event_list
+}
+
+// File operations of a single job, run with contiguous (test_9),
+// linked (test_10) and indexed (test_11) allocation. The files grow
+// interleaved, so the contiguous file has to move. The last write
+// needs every free block: it fails with contiguous allocation, for
+// lack of a long enough run, and with indexed allocation, whose
+// index blocks take part of the disk.
+pub fn test_9() -> EventList {
+    let mut event_list: EventList = EventList::new();
+    event_list.push(999, String::from("Encerramento"), Metadata::JobArrival(0, 0, SimDuration::from(0)));
+    event_list.push(10, String::from("Chegada de job"), Metadata::JobArrival(1, 20, SimDuration::from(80)));
+    event_list.push(12, String::from("Criacao de diretorio"), Metadata::CreateDirectory(1, String::from("/home")));
+    event_list.push(13, String::from("Criacao de arquivo"), Metadata::CreateFile(1, String::from("/home/log")));
+    event_list.push(14, String::from("Criacao de arquivo"), Metadata::CreateFile(1, String::from("/home/dados")));
+    event_list.push(15, String::from("Escrita em arquivo"), Metadata::WriteFile(1, String::from("/home/log"), 700));
+    event_list.push(16, String::from("Escrita em arquivo"), Metadata::WriteFile(1, String::from("/home/dados"), 1500));
+    event_list.push(17, String::from("Criacao de arquivo"), Metadata::CreateFile(1, String::from("/home/aux")));
+    event_list.push(18, String::from("Escrita em arquivo"), Metadata::WriteFile(1, String::from("/home/aux"), 512));
+    event_list.push(20, String::from("Escrita em arquivo"), Metadata::WriteFile(1, String::from("/home/log"), 600));
+    event_list.push(25, String::from("Leitura de arquivo"), Metadata::ReadFile(1, String::from("/home/log"), 1000, 200));
+    event_list.push(30, String::from("Remocao de arquivo"), Metadata::DeleteFile(1, String::from("/home/dados")));
+    event_list.push(35, String::from("Criacao de arquivo"), Metadata::CreateFile(1, String::from("/home/tmp")));
+    event_list.push(36, String::from("Escrita em arquivo"), Metadata::WriteFile(1, String::from("/home/tmp"), 2000));
+    event_list.push(40, String::from("Escrita em arquivo"), Metadata::WriteFile(1, String::from("/home/log"), 4200));
+    event_list.push(45, String::from("Leitura de arquivo"), Metadata::ReadFile(1, String::from("/home/log"), 1200, 100));
+    event_list.push(50, String::from("Remocao de arquivo"), Metadata::DeleteFile(1, String::from("/home")));
+
+    event_list
+}
+
+// Disk requests served with FCFS (test_12), SSTF (test_13), SCAN
+// (test_14), C-SCAN (test_15) and LOOK (test_16). Half of the
+// requests arrive while the disk is already busy.
+pub fn test_12() -> EventList {
+    let mut event_list: EventList = EventList::new();
+    event_list.push(999, String::from("Encerramento"), Metadata::JobArrival(0, 0, SimDuration::from(0)));
+    event_list.push(10, String::from("Chegada de job"), Metadata::JobArrival(1, 20, SimDuration::from(20)));
+    event_list.push(12, String::from("Chegada de job"), Metadata::JobArrival(2, 20, SimDuration::from(20)));
+    for (job, cylinder) in [(1, 98), (2, 183), (1, 37), (2, 122)] {
+        event_list.push(20, String::from("Requisicao de disco"), Metadata::DiskRequest(job, cylinder));
+    }
+    for (job, cylinder) in [(1, 14), (2, 124), (1, 65), (2, 67)] {
+        event_list.push(30, String::from("Requisicao de disco"), Metadata::DiskRequest(job, cylinder));
+    }
+
+    event_list
+}
+
+// A cpu-bound job sharing the processor with two I/O-bound jobs,
+// which compete for the same tape drive.
+pub fn test_17() -> EventList {
+    let mut event_list: EventList = EventList::new();
+    let io_bound = || {
+        vec![
+            Burst::Cpu(4),
+            Burst::Io(String::from("Fita"), 12),
+            Burst::Cpu(4),
+            Burst::Io(String::from("Fita"), 12),
+            Burst::Cpu(4),
+        ]
+    };
+    event_list.push(999, String::from("Encerramento"), Metadata::JobArrival(0, 0, SimDuration::from(0)));
+    event_list.push(10, String::from("Chegada de job"), Metadata::JobArrival(1, 30, SimDuration::from(40)));
+    event_list.push(11, String::from("Chegada de job com perfil"), Metadata::ProfiledJobArrival(2, 20, io_bound()));
+    event_list.push(12, String::from("Chegada de job com perfil"), Metadata::ProfiledJobArrival(3, 20, io_bound()));
+
+    event_list
+}
+
+// Jobs submitted as JCL decks. The deck at instant 15 has no $END
+// card and the one at instant 25 has no $MEM card, so both are
+// rejected and only jobs 1 and 3 run.
+pub fn test_19() -> EventList {
+    let mut event_list: EventList = EventList::new();
+    let decks = [
+        (10, "$JOB 1\n$MEM 40\n$TIME 25\n$END"),
+        (15, "$JOB 2\n$MEM 30\n$TIME 10\nPROGRAMA"),
+        (20, "$JOB 3\n$MEM 20\n$TIME 15\n$PRIORITY 2\nPROGRAMA\nDADOS\n$END"),
+        (25, "$JOB 4\n$TIME 10\n$END"),
+    ];
+    event_list.push(999, String::from("Encerramento"), Metadata::JobArrival(0, 0, SimDuration::from(0)));
+    for (time, deck) in decks {
+        event_list.push(time, String::from("Chegada de deck"), Metadata::JobDeck(String::from(deck)));
+    }
+
+    event_list
+}
+
+// Arrivals of test_2 with memory accesses: job 1 stays within its
+// segment, job 2 reaches past its limit and is cancelled.
+pub fn test_23() -> EventList {
+    let mut event_list = test_2();
+    event_list.push(15, String::from("Acesso a memoria de job"), Metadata::MemoryAccess(1, 39));
+    event_list.push(25, String::from("Acesso a memoria de job"), Metadata::MemoryAccess(2, 10));
+    event_list.push(28, String::from("Acesso a memoria de job"), Metadata::MemoryAccess(2, 60));
+
+    event_list
+}
+
+// Jobs running programs: jobs 1 and 2 write values while sharing the
+// processor, job 3 stores past the end of its segment.
+pub fn test_24() -> EventList {
+    let countdown = "
+              LOAD  R0, count
+              LI    R1, 1
+        loop: JZ    R0, end
+              SYS   1
+              SUB   R0, R0, R1
+              JMP   loop
+        end:  SYS   0
+        count: WORD 3
+    ";
+    let tens = "
+              LI    R1, 3
+              LI    R2, 10
+              LI    R3, 1
+        loop: JZ    R1, end
+              ADD   R0, R0, R2
+              SYS   1
+              SUB   R1, R1, R3
+              JMP   loop
+        end:  SYS   0
+    ";
+    let overflow = "
+              LI    R0, 7
+              STORE R0, 10
+              SYS   0
+    ";
+    let mut event_list: EventList = EventList::new();
+    event_list.push(999, String::from("Encerramento"), Metadata::JobArrival(0, 0, SimDuration::from(0)));
+    event_list.push(10, String::from("Chegada de job com programa"), Metadata::ProgramJobArrival(1, 20, SimDuration::from(40), String::from(countdown)));
+    event_list.push(12, String::from("Chegada de job com programa"), Metadata::ProgramJobArrival(2, 20, SimDuration::from(40), String::from(tens)));
+    event_list.push(14, String::from("Chegada de job com programa"), Metadata::ProgramJobArrival(3, 10, SimDuration::from(20), String::from(overflow)));
 
     event_list
 }
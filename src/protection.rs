@@ -0,0 +1,60 @@
+// In this file it is implemented the memory protection of the jobs.
+// Each job in memory has a base register, the start of its segment,
+// and a limit register, the size of the segment. The addresses a job
+// generates are logical, counted from 0; the base is added to them to
+// find the physical address, and an address beyond the limit raises a
+// protection fault instead of reaching the memory of another job.
+// Programs placed by the relocating loader already hold physical
+// addresses, which are only checked against the segment.
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BaseLimit {
+    pub base: i32,
+    pub limit: i32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ProtectionFault {
+    // Logical address the job tried to use
+    pub address: i32,
+    pub limit: i32,
+}
+
+impl fmt::Display for ProtectionFault {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "endereco logico {} fora do limite {}", self.address, self.limit)
+    }
+}
+
+impl BaseLimit {
+    // Physical address of the logical one
+    pub fn translate(&self, address: i32) -> Result<i32, ProtectionFault> {
+        if (0..self.limit).contains(&address) {
+            Ok(self.base + address)
+        } else {
+            Err(ProtectionFault { address, limit: self.limit })
+        }
+    }
+
+    // The physical address itself, if it is inside the segment
+    pub fn check(&self, address: i32) -> Result<i32, ProtectionFault> {
+        self.translate(address - self.base).map(|_| address)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_translation_checks_the_limit() {
+        let registers = BaseLimit { base: 40, limit: 30 };
+        assert_eq!(registers.translate(0), Ok(40));
+        assert_eq!(registers.translate(29), Ok(69));
+        assert_eq!(registers.translate(30), Err(ProtectionFault { address: 30, limit: 30 }));
+        assert!(registers.translate(-1).is_err());
+        assert_eq!(registers.check(69), Ok(69));
+        assert_eq!(registers.check(39), Err(ProtectionFault { address: -1, limit: 30 }));
+    }
+}
@@ -0,0 +1,501 @@
+// In this file it is implemented the resource manager. Besides
+// memory, jobs may use resources such as tape drives and printers:
+// each job declares its maximum claim on every resource type and
+// then requests and releases units during its execution. Requests
+// are only granted when the Banker's algorithm finds the resulting
+// state safe; otherwise they wait until some job releases units.
+// Alternatively, requests are granted whenever there are free units
+// and deadlocks are detected periodically and broken afterwards.
+use crate::time::SimDuration;
+use std::collections::BTreeMap;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ResourceError {
+    // The job issued a request without declaring its claims
+    UndeclaredJob(i32),
+    // The vector does not have one entry per resource type
+    WrongLength(i32, usize),
+    // A negative number of units
+    NegativeUnits(i32),
+    ClaimExceedsTotal(i32),
+    RequestExceedsClaim(i32),
+    ReleaseExceedsAllocation(i32),
+    // A deadlock check every period <= 0 timesteps would run again
+    // in the same instant forever
+    NonPositivePeriod(SimDuration),
+}
+
+impl fmt::Display for ResourceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ResourceError::UndeclaredJob(job) => write!(f, "job {} nao declarou seus recursos maximos", job),
+            ResourceError::WrongLength(job, len) => write!(f, "job {} informou {} tipos de recurso", job, len),
+            ResourceError::NegativeUnits(job) => write!(f, "job {} informou um numero negativo de unidades", job),
+            ResourceError::ClaimExceedsTotal(job) => write!(f, "declaracao do job {} excede o total do sistema", job),
+            ResourceError::RequestExceedsClaim(job) => write!(f, "requisicao do job {} excede sua declaracao", job),
+            ResourceError::ReleaseExceedsAllocation(job) => write!(f, "job {} liberou mais do que possui", job),
+            ResourceError::NonPositivePeriod(period) => write!(f, "periodo de deteccao de deadlock {:?} nao e positivo", period),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RequestOutcome {
+    Granted,
+    // Not enough free units right now
+    Unavailable,
+    // Enough free units, but granting would lead to an unsafe state
+    Unsafe,
+}
+
+// How a deadlock detected by the periodic check is broken
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RecoveryStrategy {
+    // Kill the deadlocked job with the lowest priority
+    KillLowestPriority,
+    // Take every unit from the deadlocked job with the lowest
+    // priority and roll it back to its start: its processing time is
+    // restored and it asks again for everything it had
+    PreemptResources,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ResourcePolicy {
+    // Banker's algorithm: unsafe requests are delayed
+    #[default]
+    Avoidance,
+    // Requests are granted whenever possible; every period timesteps
+    // the system looks for deadlocks and recovers from them
+    Detection { period: DetectionPeriod, recovery: RecoveryStrategy },
+}
+
+impl ResourcePolicy {
+    pub fn detection(period: SimDuration, recovery: RecoveryStrategy) -> Result<Self, ResourceError> {
+        Ok(ResourcePolicy::Detection { period: DetectionPeriod::new(period)?, recovery })
+    }
+}
+
+// Time between two deadlock checks. It is always positive, so the
+// next check is never due in the instant the current one runs.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DetectionPeriod(SimDuration);
+
+impl DetectionPeriod {
+    pub fn new(period: SimDuration) -> Result<Self, ResourceError> {
+        if period <= SimDuration::ZERO {
+            return Err(ResourceError::NonPositivePeriod(period));
+        }
+        Ok(DetectionPeriod(period))
+    }
+
+    pub fn duration(self) -> SimDuration {
+        self.0
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ResourceManager {
+    policy: ResourcePolicy,
+    names: Vec<String>,
+    total: Vec<i32>,
+    available: Vec<i32>,
+    max: BTreeMap<i32, Vec<i32>>,
+    allocation: BTreeMap<i32, Vec<i32>>,
+    waiting: Vec<(i32, Vec<i32>)>,
+}
+
+impl ResourceManager {
+    pub fn new(resources: Vec<(String, i32)>) -> Self {
+        let (names, total): (Vec<String>, Vec<i32>) = resources.into_iter().unzip();
+        ResourceManager {
+            policy: ResourcePolicy::Avoidance,
+            names,
+            available: total.clone(),
+            total,
+            max: BTreeMap::new(),
+            allocation: BTreeMap::new(),
+            waiting: Vec::new(),
+        }
+    }
+
+    pub fn with_policy(resources: Vec<(String, i32)>, policy: ResourcePolicy) -> Self {
+        ResourceManager { policy, ..ResourceManager::new(resources) }
+    }
+
+    pub fn policy(&self) -> ResourcePolicy {
+        self.policy
+    }
+
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+
+    pub fn available(&self) -> &[i32] {
+        &self.available
+    }
+
+    pub fn allocation(&self, job_id: i32) -> Vec<i32> {
+        self.allocation.get(&job_id).cloned().unwrap_or_else(|| vec![0; self.total.len()])
+    }
+
+    pub fn need(&self, job_id: i32) -> Vec<i32> {
+        let allocation = self.allocation(job_id);
+        match self.max.get(&job_id) {
+            Some(max) => max.iter().zip(&allocation).map(|(m, a)| m - a).collect(),
+            None => vec![0; self.total.len()],
+        }
+    }
+
+    // Jobs holding at least one unit, in id order
+    pub fn holders(&self) -> Vec<i32> {
+        self.allocation
+            .iter()
+            .filter(|(_, allocation)| allocation.iter().any(|&units| units > 0))
+            .map(|(&job_id, _)| job_id)
+            .collect()
+    }
+
+    // Whether any job has declared claims and not left the system yet
+    pub fn has_jobs(&self) -> bool {
+        !self.max.is_empty()
+    }
+
+    // Pending requests, in arrival order
+    pub fn waiting(&self) -> &[(i32, Vec<i32>)] {
+        &self.waiting
+    }
+
+    pub fn declare(&mut self, job_id: i32, claim: Vec<i32>) -> Result<(), ResourceError> {
+        self.check_units(job_id, &claim)?;
+        if claim.iter().zip(&self.total).any(|(c, t)| c > t) {
+            return Err(ResourceError::ClaimExceedsTotal(job_id));
+        }
+        self.max.insert(job_id, claim);
+        Ok(())
+    }
+
+    pub fn request(&mut self, job_id: i32, request: Vec<i32>) -> Result<RequestOutcome, ResourceError> {
+        self.check_units(job_id, &request)?;
+        if !self.max.contains_key(&job_id) {
+            return Err(ResourceError::UndeclaredJob(job_id));
+        }
+        if !fits(&request, &self.need(job_id)) {
+            return Err(ResourceError::RequestExceedsClaim(job_id));
+        }
+
+        let outcome = self.try_grant(job_id, &request);
+        if outcome != RequestOutcome::Granted {
+            self.waiting.push((job_id, request));
+        }
+        Ok(outcome)
+    }
+
+    // Drops the latest pending request of the job
+    pub fn withdraw_request(&mut self, job_id: i32) {
+        if let Some(position) = self.waiting.iter().rposition(|(waiting_job, _)| *waiting_job == job_id) {
+            self.waiting.remove(position);
+        }
+    }
+
+    pub fn release(&mut self, job_id: i32, release: Vec<i32>) -> Result<(), ResourceError> {
+        self.check_units(job_id, &release)?;
+        let allocation = self.allocation(job_id);
+        if !fits(&release, &allocation) {
+            return Err(ResourceError::ReleaseExceedsAllocation(job_id));
+        }
+        let allocation: Vec<i32> = allocation.iter().zip(&release).map(|(a, r)| a - r).collect();
+        for (available, units) in self.available.iter_mut().zip(&release) {
+            *available += units;
+        }
+        self.allocation.insert(job_id, allocation);
+        Ok(())
+    }
+
+    // Forgets the job: releases everything it holds and drops its
+    // claims and pending requests. Returns the units released.
+    pub fn release_all(&mut self, job_id: i32) -> Vec<i32> {
+        let allocation = self.allocation.remove(&job_id).unwrap_or_else(|| vec![0; self.total.len()]);
+        for (available, units) in self.available.iter_mut().zip(&allocation) {
+            *available += units;
+        }
+        self.max.remove(&job_id);
+        self.waiting.retain(|(waiting_job, _)| *waiting_job != job_id);
+        allocation
+    }
+
+    // Takes every unit the job holds and replaces its pending request
+    // by one for everything it had, as if it started over. Its claims
+    // are kept. Returns the units taken.
+    pub fn preempt(&mut self, job_id: i32) -> Vec<i32> {
+        let allocation = self.allocation.remove(&job_id).unwrap_or_else(|| vec![0; self.total.len()]);
+        for (available, units) in self.available.iter_mut().zip(&allocation) {
+            *available += units;
+        }
+
+        let mut request = allocation.clone();
+        for (_, pending) in self.waiting.iter().filter(|(waiting_job, _)| *waiting_job == job_id) {
+            for (units, pending) in request.iter_mut().zip(pending) {
+                *units += pending;
+            }
+        }
+        self.waiting.retain(|(waiting_job, _)| *waiting_job != job_id);
+        self.waiting.push((job_id, request));
+        allocation
+    }
+
+    // Deadlock detection algorithm: like the safety check, but using
+    // the pending requests instead of the remaining claims. Jobs that
+    // can never have their requests granted are deadlocked.
+    pub fn detect_deadlock(&self) -> Vec<i32> {
+        let mut work = self.available.clone();
+        let mut jobs: Vec<i32> = self.holders();
+        for (job_id, _) in &self.waiting {
+            if !jobs.contains(job_id) {
+                jobs.push(*job_id);
+            }
+        }
+        jobs.sort();
+
+        let mut finished: Vec<i32> = jobs
+            .iter()
+            .copied()
+            .filter(|&job_id| self.allocation(job_id).iter().all(|&units| units == 0))
+            .collect();
+
+        loop {
+            let next = jobs
+                .iter()
+                .find(|job_id| !finished.contains(job_id) && fits(&self.pending_request(**job_id), &work));
+            match next {
+                Some(&job_id) => {
+                    for (work, units) in work.iter_mut().zip(self.allocation(job_id)) {
+                        *work += units;
+                    }
+                    finished.push(job_id);
+                }
+                None => break,
+            }
+        }
+        jobs.into_iter().filter(|job_id| !finished.contains(job_id)).collect()
+    }
+
+    // Wait-for graph: a job waits for another if its pending request
+    // asks for a resource type that is exhausted and held by the other
+    pub fn wait_for_graph(&self) -> BTreeMap<i32, Vec<i32>> {
+        let mut graph = BTreeMap::new();
+        for (job_id, request) in &self.waiting {
+            let mut holders: Vec<i32> = Vec::new();
+            for (kind, units) in request.iter().enumerate() {
+                if *units <= self.available[kind] {
+                    continue;
+                }
+                for (&holder, allocation) in &self.allocation {
+                    if holder != *job_id && allocation[kind] > 0 && !holders.contains(&holder) {
+                        holders.push(holder);
+                    }
+                }
+            }
+            graph.entry(*job_id).or_insert_with(Vec::new).extend(holders);
+        }
+        graph
+    }
+
+    fn pending_request(&self, job_id: i32) -> Vec<i32> {
+        let mut request = vec![0; self.total.len()];
+        for (_, pending) in self.waiting.iter().filter(|(waiting_job, _)| *waiting_job == job_id) {
+            for (units, pending) in request.iter_mut().zip(pending) {
+                *units += pending;
+            }
+        }
+        request
+    }
+
+    // Grants every pending request that can now be granted safely,
+    // oldest first. Returns the jobs whose requests were granted.
+    pub fn grant_waiting(&mut self) -> Vec<i32> {
+        let mut granted = Vec::new();
+        let mut index = 0;
+        while index < self.waiting.len() {
+            let (job_id, request) = self.waiting[index].clone();
+            if self.try_grant(job_id, &request) == RequestOutcome::Granted {
+                self.waiting.remove(index);
+                granted.push(job_id);
+                // A grant may unblock older requests again
+                index = 0;
+            } else {
+                index += 1;
+            }
+        }
+        granted
+    }
+
+    // Banker's safety algorithm: the state is safe if there is an
+    // order in which every job can obtain its remaining need and
+    // finish, returning what it holds.
+    pub fn is_safe(&self) -> bool {
+        let mut work = self.available.clone();
+        let mut finished: Vec<i32> = Vec::new();
+        let jobs: Vec<i32> = self.max.keys().copied().collect();
+
+        loop {
+            let next = jobs
+                .iter()
+                .find(|job_id| !finished.contains(job_id) && fits(&self.need(**job_id), &work));
+            match next {
+                Some(&job_id) => {
+                    for (work, units) in work.iter_mut().zip(self.allocation(job_id)) {
+                        *work += units;
+                    }
+                    finished.push(job_id);
+                }
+                None => return finished.len() == jobs.len(),
+            }
+        }
+    }
+
+    fn try_grant(&mut self, job_id: i32, request: &[i32]) -> RequestOutcome {
+        if !fits(request, &self.available) {
+            return RequestOutcome::Unavailable;
+        }
+
+        // Pretend to allocate and roll back if the state is unsafe
+        let previous = self.allocation(job_id);
+        let allocation: Vec<i32> = previous.iter().zip(request).map(|(a, r)| a + r).collect();
+        self.allocation.insert(job_id, allocation);
+        for (available, units) in self.available.iter_mut().zip(request) {
+            *available -= units;
+        }
+
+        if self.policy != ResourcePolicy::Avoidance || self.is_safe() {
+            RequestOutcome::Granted
+        } else {
+            self.allocation.insert(job_id, previous);
+            for (available, units) in self.available.iter_mut().zip(request) {
+                *available += units;
+            }
+            RequestOutcome::Unsafe
+        }
+    }
+
+    fn check_units(&self, job_id: i32, units: &[i32]) -> Result<(), ResourceError> {
+        if units.len() != self.total.len() {
+            Err(ResourceError::WrongLength(job_id, units.len()))
+        } else if units.iter().any(|&units| units < 0) {
+            Err(ResourceError::NegativeUnits(job_id))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+fn fits(units: &[i32], limit: &[i32]) -> bool {
+    units.iter().zip(limit).all(|(u, l)| u <= l)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tapes(total: i32) -> ResourceManager {
+        ResourceManager::new(vec![(String::from("Fita"), total)])
+    }
+
+    #[test]
+    fn test_unsafe_request_is_delayed() {
+        let mut manager = tapes(3);
+        manager.declare(1, vec![3]).unwrap();
+        manager.declare(2, vec![2]).unwrap();
+        assert_eq!(manager.request(1, vec![1]), Ok(RequestOutcome::Granted));
+        assert_eq!(manager.request(2, vec![1]), Ok(RequestOutcome::Granted));
+
+        // Job 1 would leave one free unit that neither job can finish with
+        assert_eq!(manager.request(1, vec![1]), Ok(RequestOutcome::Unsafe));
+        assert_eq!(manager.allocation(1), vec![1]);
+        assert_eq!(manager.available(), &[1]);
+
+        assert_eq!(manager.request(2, vec![1]), Ok(RequestOutcome::Granted));
+        assert!(manager.grant_waiting().is_empty());
+
+        manager.release(2, vec![2]).unwrap();
+        assert_eq!(manager.grant_waiting(), vec![1]);
+        assert_eq!(manager.allocation(1), vec![2]);
+    }
+
+    #[test]
+    fn test_request_beyond_claim_is_rejected() {
+        let mut manager = tapes(3);
+        manager.declare(1, vec![1]).unwrap();
+
+        assert_eq!(manager.request(1, vec![2]), Err(ResourceError::RequestExceedsClaim(1)));
+        assert_eq!(manager.request(2, vec![1]), Err(ResourceError::UndeclaredJob(2)));
+        assert_eq!(manager.declare(3, vec![4]), Err(ResourceError::ClaimExceedsTotal(3)));
+    }
+
+    #[test]
+    fn test_negative_units_are_rejected() {
+        let mut manager = tapes(3);
+        assert_eq!(manager.declare(1, vec![-1]), Err(ResourceError::NegativeUnits(1)));
+        manager.declare(1, vec![2]).unwrap();
+        manager.request(1, vec![1]).unwrap();
+
+        // Neither adds units nobody released nor takes units out
+        assert_eq!(manager.request(1, vec![-3]), Err(ResourceError::NegativeUnits(1)));
+        assert_eq!(manager.release(1, vec![-3]), Err(ResourceError::NegativeUnits(1)));
+        assert_eq!(manager.available(), &[2]);
+        assert_eq!(manager.allocation(1), vec![1]);
+        assert!(manager.waiting().is_empty());
+    }
+
+    #[test]
+    fn test_detection_finds_circular_wait() {
+        let resources = vec![(String::from("Fita"), 1), (String::from("Impressora"), 1)];
+        let policy = ResourcePolicy::detection(SimDuration::from(10), RecoveryStrategy::KillLowestPriority).unwrap();
+        let mut manager = ResourceManager::with_policy(resources, policy);
+        manager.declare(1, vec![1, 1]).unwrap();
+        manager.declare(2, vec![1, 1]).unwrap();
+        manager.declare(3, vec![1, 0]).unwrap();
+
+        // Without avoidance both first requests are granted
+        assert_eq!(manager.request(1, vec![1, 0]), Ok(RequestOutcome::Granted));
+        assert_eq!(manager.request(2, vec![0, 1]), Ok(RequestOutcome::Granted));
+        assert!(manager.detect_deadlock().is_empty());
+
+        manager.request(1, vec![0, 1]).unwrap();
+        manager.request(2, vec![1, 0]).unwrap();
+        manager.request(3, vec![1, 0]).unwrap();
+        assert_eq!(manager.detect_deadlock(), vec![1, 2]);
+        assert_eq!(manager.wait_for_graph().get(&1), Some(&vec![2]));
+        assert_eq!(manager.wait_for_graph().get(&3), Some(&vec![1]));
+
+        // Preempting job 2 lets job 1 go on; job 2 asks for everything again
+        assert_eq!(manager.preempt(2), vec![0, 1]);
+        assert_eq!(manager.grant_waiting(), vec![1]);
+        assert!(manager.detect_deadlock().is_empty());
+        assert_eq!(manager.waiting(), &[(3, vec![1, 0]), (2, vec![1, 1])]);
+    }
+
+    #[test]
+    fn test_detection_period_must_be_positive() {
+        let recovery = RecoveryStrategy::PreemptResources;
+        for ticks in [0, -5] {
+            let period = SimDuration::from(ticks);
+            assert_eq!(ResourcePolicy::detection(period, recovery), Err(ResourceError::NonPositivePeriod(period)));
+        }
+        let policy = ResourcePolicy::detection(SimDuration::from(1), recovery).unwrap();
+        assert_eq!(policy, ResourcePolicy::Detection { period: DetectionPeriod::new(SimDuration::from(1)).unwrap(), recovery });
+    }
+
+    #[test]
+    fn test_release_all_returns_units() {
+        let mut manager = tapes(2);
+        manager.declare(1, vec![2]).unwrap();
+        manager.declare(2, vec![2]).unwrap();
+        manager.request(1, vec![2]).unwrap();
+        assert_eq!(manager.request(2, vec![1]), Ok(RequestOutcome::Unavailable));
+
+        assert_eq!(manager.release_all(1), vec![2]);
+        assert_eq!(manager.grant_waiting(), vec![2]);
+        assert_eq!(manager.available(), &[1]);
+    }
+}
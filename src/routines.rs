@@ -1,6 +1,20 @@
 use std::collections::HashMap;
 use crate::system_abstractions::{Job, ControlModule};
-use crate::event_list::{Metadata};
+use crate::event_list::{EventHandle, Metadata};
+use crate::jcl::parse_deck;
+use crate::job_profile::JobProfile;
+use crate::machine::{assemble, Machine, Stop};
+use crate::resources::{RecoveryStrategy, ResourcePolicy};
+use crate::simulation_error::SimulationError;
+use crate::time::{SimDuration, SimTime};
+
+// Default processing time a job gets each time it is handed the
+// processor
+pub const TIME_SLICE: SimDuration = SimDuration::from_ticks(10);
+
+// Default time the loader takes to copy and relocate each word of a
+// program
+pub const LOAD_TIME_PER_WORD: SimDuration = SimDuration::from_ticks(1);
 
 pub fn select_routine<'a>(event_to_routine: &'a HashMap<&'a str, &'a str>, event_name: &'a str) -> &'a str {
     match event_to_routine.get(event_name) {
@@ -10,12 +24,18 @@ pub fn select_routine<'a>(event_to_routine: &'a HashMap<&'a str, &'a str>, event
 }
 
 pub trait Runnable {
-    fn run(&self, control_module: &ControlModule);
+    fn run(&self, control_module: &ControlModule) -> Result<(), SimulationError>;
 }
 
+// Builds the routine that handles an event from its metadata.
+// Factories registered on the control module take precedence over
+// the built-in routines of the same name.
+pub type RoutineFactory = Box<dyn Fn(&Metadata) -> Box<dyn Runnable> + Send>;
+
 pub fn create_routine(routine: &str, metadata: &Metadata) -> Box<dyn Runnable> {
     match routine {
         "JobArrival" => Box::new(JobArrival{metadata: metadata.clone()}),
+        "SubmitDeck" => Box::new(SubmitDeck{metadata: metadata.clone()}),
         "JobEntrance" => Box::new(JobEntrance{metadata: metadata.clone()}),
         "RequestMemory" => Box::new(RequestMemory{metadata: metadata.clone()}),
         "RequestCPU" => Box::new(RequestCPU{metadata: metadata.clone()}),
@@ -24,6 +44,28 @@ pub fn create_routine(routine: &str, metadata: &Metadata) -> Box<dyn Runnable> {
         "FreeMemory" => Box::new(FreeMemory{metadata: metadata.clone()}),
         "ExitSystem" => Box::new(ExitSystem{metadata: metadata.clone()}),
         "PauseJob" => Box::new(PauseJob{metadata: metadata.clone()}),
+        "KillJob" => Box::new(KillJob{metadata: metadata.clone()}),
+        "DeclareResources" => Box::new(DeclareResources{metadata: metadata.clone()}),
+        "RequestResources" => Box::new(RequestResources{metadata: metadata.clone()}),
+        "ReleaseResources" => Box::new(ReleaseResources{metadata: metadata.clone()}),
+        "SetPriority" => Box::new(SetPriority{metadata: metadata.clone()}),
+        "DetectDeadlock" => Box::new(DetectDeadlock),
+        "SemaphoreWait" => Box::new(SemaphoreWait{metadata: metadata.clone()}),
+        "SemaphoreSignal" => Box::new(SemaphoreSignal{metadata: metadata.clone()}),
+        "WakeJob" => Box::new(WakeJob{metadata: metadata.clone()}),
+        "SendMessage" => Box::new(SendMessage{metadata: metadata.clone()}),
+        "ReceiveMessage" => Box::new(ReceiveMessage{metadata: metadata.clone()}),
+        "DeliverMessage" => Box::new(DeliverMessage{metadata: metadata.clone()}),
+        "FileOperation" => Box::new(FileOperation{metadata: metadata.clone()}),
+        "DiskRequest" => Box::new(DiskRequest{metadata: metadata.clone()}),
+        "StartIo" => Box::new(StartIo{metadata: metadata.clone()}),
+        "EndCardRead" => Box::new(EndCardRead{metadata: metadata.clone()}),
+        "EndPrint" => Box::new(EndPrint{metadata: metadata.clone()}),
+        "EndIo" => Box::new(EndIo{metadata: metadata.clone()}),
+        "StartDiskAccess" => Box::new(StartDiskAccess),
+        "EndDiskAccess" => Box::new(EndDiskAccess),
+        "MemoryAccess" => Box::new(MemoryAccess{metadata: metadata.clone()}),
+        "ProtectionFault" => Box::new(ProtectionFault{metadata: metadata.clone()}),
         _ => Box::new(DefaultRoutine), // Handle unknown routines
     }
 }
@@ -34,6 +76,9 @@ pub fn create_event_to_routine() -> HashMap<&'static str, &'static str> {
 
     // Insert key-value pairs into the HashMap
     event_to_routine.insert("Chegada de job", "JobArrival");
+    event_to_routine.insert("Chegada de job com perfil", "JobArrival");
+    event_to_routine.insert("Chegada de job com programa", "JobArrival");
+    event_to_routine.insert("Chegada de deck", "SubmitDeck");
     event_to_routine.insert("Ingresso de job", "JobEntrance");
     event_to_routine.insert("Requisicao de memoria de job", "RequestMemory");
     event_to_routine.insert("Requisicao de processador de job", "RequestCPU");
@@ -42,6 +87,32 @@ pub fn create_event_to_routine() -> HashMap<&'static str, &'static str> {
     event_to_routine.insert("Liberacao de memoria job", "FreeMemory");
     event_to_routine.insert("Saida do sistema job", "ExitSystem");
     event_to_routine.insert("Pause job", "PauseJob");
+    event_to_routine.insert("Cancelamento de job", "KillJob");
+    event_to_routine.insert("Declaracao de recursos de job", "DeclareResources");
+    event_to_routine.insert("Requisicao de recursos de job", "RequestResources");
+    event_to_routine.insert("Liberacao de recursos de job", "ReleaseResources");
+    event_to_routine.insert("Definicao de prioridade de job", "SetPriority");
+    event_to_routine.insert("Deteccao de deadlock", "DetectDeadlock");
+    event_to_routine.insert("Wait em semaforo de job", "SemaphoreWait");
+    event_to_routine.insert("Signal em semaforo de job", "SemaphoreSignal");
+    event_to_routine.insert("Despertar de job", "WakeJob");
+    event_to_routine.insert("Envio de mensagem de job", "SendMessage");
+    event_to_routine.insert("Recepcao de mensagem de job", "ReceiveMessage");
+    event_to_routine.insert("Entrega de mensagem", "DeliverMessage");
+    event_to_routine.insert("Criacao de diretorio", "FileOperation");
+    event_to_routine.insert("Criacao de arquivo", "FileOperation");
+    event_to_routine.insert("Escrita em arquivo", "FileOperation");
+    event_to_routine.insert("Leitura de arquivo", "FileOperation");
+    event_to_routine.insert("Remocao de arquivo", "FileOperation");
+    event_to_routine.insert("Requisicao de disco", "DiskRequest");
+    event_to_routine.insert("Inicio de acesso ao disco", "StartDiskAccess");
+    event_to_routine.insert("Fim de acesso ao disco", "EndDiskAccess");
+    event_to_routine.insert("Inicio de E/S de job", "StartIo");
+    event_to_routine.insert("Fim de E/S de job", "EndIo");
+    event_to_routine.insert("Fim de leitura de cartoes de job", "EndCardRead");
+    event_to_routine.insert("Fim de impressao de job", "EndPrint");
+    event_to_routine.insert("Acesso a memoria de job", "MemoryAccess");
+    event_to_routine.insert("Falha de protecao de job", "ProtectionFault");
     //event_to_routine.insert("", "");
     
     event_to_routine
@@ -51,8 +122,9 @@ pub fn create_event_to_routine() -> HashMap<&'static str, &'static str> {
 
 struct DefaultRoutine;
 impl Runnable for DefaultRoutine {
-    fn run(&self, control_module: &ControlModule) {
+    fn run(&self, _control_module: &ControlModule) -> Result<(), SimulationError> {
         println!("DefaultRoutine is running!");
+        Ok(())
     }
 }
 
@@ -60,36 +132,143 @@ struct JobArrival{
     metadata: Metadata,
 }
 
+// Job, memory size, processing time, profile and program source
+type Arrival = (i32, i32, SimDuration, Option<JobProfile>, Option<String>);
+
 impl JobArrival {
-    fn unwrap_metadata(&self) -> (i32, i32, i32) {
+    fn unwrap_metadata(&self) -> Result<Arrival, SimulationError> {
         match &self.metadata {
-            Metadata::JobArrival(num, mem, cpu) => (*num, *mem, *cpu),
-            _ => (0, 0, 0),
+            Metadata::JobArrival(num, mem, cpu) => Ok((*num, *mem, *cpu, None, None)),
+            Metadata::ProfiledJobArrival(num, mem, bursts) => {
+                let profile = JobProfile::new(bursts.clone());
+                Ok((*num, *mem, profile.cpu_time(), Some(profile), None))
+            }
+            Metadata::ProgramJobArrival(num, mem, cpu, source) => {
+                Ok((*num, *mem, *cpu, None, Some(source.clone())))
+            }
+            _ => Err(SimulationError::UnexpectedMetadata("JobArrival", self.metadata.clone())),
         }
     }
 }
 
 impl Runnable for JobArrival {
-    fn run(&self, control_module: &ControlModule) {
+    fn run(&self, control_module: &ControlModule) -> Result<(), SimulationError> {
         println!("JobArrival esta rodando!");
 
         // Add the new job to the system entry queue
-        let job_number = self.unwrap_metadata().0;
-        let job_memory_size = self.unwrap_metadata().1;
-        let job_cpu_time = self.unwrap_metadata().2;
+        let (job_number, job_memory_size, job_cpu_time, profile, source) = self.unwrap_metadata()?;
         let new_job = Job {id: job_number, state: 1, memory_size: job_memory_size, cpu_time: job_cpu_time};
-        
-        if control_module.eq_is_empty() {
-
-            // Add the job entrance event to be immediately treated
+        control_module.record_job_arrival(job_number);
+        if let Some(profile) = profile {
+            control_module.set_job_profile(job_number, profile);
+        }
 
-            control_module.add_event(0, "Ingresso de job".to_string(), Metadata::JobEntrance(new_job));
+        // O programa do job e montado agora e carregado no inicio do
+        // seu segmento quando a memoria for alocada; um programa com
+        // erro ou maior que a memoria pedida e rejeitado e o job nao
+        // ingressa no sistema
+        if let Some(source) = source {
+            let machine = assemble(&source).map_err(|error| error.to_string()).and_then(|program| {
+                let words = program.len();
+                Machine::new(program, job_memory_size)
+                    .ok_or(format!("programa de {} palavras nao cabe em {}k", words, job_memory_size))
+            });
+            match machine {
+                Ok(machine) => control_module.set_machine(job_number, machine),
+                Err(error) => {
+                    println!("Programa do job {} rejeitado: {}", job_number, error);
+                    control_module.record_trace("Rejeicao de programa".to_string(), error);
+                    println!("JobArrival terminou!");
+                    return Ok(());
+                }
+            }
+        }
 
+        // Com spooling, o job so ingressa no sistema depois que a
+        // leitora de cartoes passa todo o seu deck para o spool
+        if let Some(end) = control_module.spool_input(&new_job) {
+            println!("Deck do job {} sera lido ate o instante {}", job_number, end);
         } else {
-            control_module.add_SEQ(new_job.clone());
+            admit_job(control_module, new_job);
         }
 
         println!("JobArrival terminou!");
+        Ok(())
+    }
+}
+
+struct SubmitDeck {
+    metadata: Metadata,
+}
+
+impl SubmitDeck {
+    fn unwrap_metadata(&self) -> Result<String, SimulationError> {
+        match &self.metadata {
+            Metadata::JobDeck(deck) => Ok(deck.clone()),
+            _ => Err(SimulationError::UnexpectedMetadata("SubmitDeck", self.metadata.clone())),
+        }
+    }
+}
+
+impl Runnable for SubmitDeck {
+    fn run(&self, control_module: &ControlModule) -> Result<(), SimulationError> {
+        println!("SubmitDeck esta rodando!");
+        // O monitor interpreta os cartoes de controle do deck. Um
+        // deck valido vira a chegada do job, para tratamento
+        // imediato, com a memoria, o tempo e a prioridade pedidos.
+        // Um deck mal formado e rejeitado e o erro vai para o trace.
+
+        let deck = self.unwrap_metadata()?;
+        match parse_deck(&deck) {
+            Ok(job) => {
+                println!("Deck do job {} aceito", job.id);
+                if let Some(priority) = job.priority {
+                    control_module.set_job_priority(job.id, priority);
+                }
+                control_module.schedule_now(
+                    "Chegada de job".to_string(),
+                    Metadata::JobArrival(job.id, job.memory_size, SimDuration::from(job.cpu_time)),
+                );
+            }
+            Err(error) => {
+                println!("Deck rejeitado: {}", error);
+                control_module.record_trace("Rejeicao de deck".to_string(), error.to_string());
+                control_module.record_deck_rejection();
+            }
+        }
+
+        println!("SubmitDeck terminou!");
+        Ok(())
+    }
+}
+
+// Um pedido que bloquearia um job que nao esta executando nem
+// aguardando o processador e recusado: o erro vai para o trace e o
+// evento nao tem efeito
+fn refuse_if_not_running<T>(
+    control_module: &ControlModule,
+    result: Result<T, SimulationError>,
+) -> Result<Option<T>, SimulationError> {
+    match result {
+        Err(SimulationError::JobNotRunning(job_id)) => {
+            let error = SimulationError::JobNotRunning(job_id);
+            println!("Pedido recusado: {}", error);
+            control_module.record_trace(format!("Pedido recusado do job {}", job_id), error.to_string());
+            Ok(None)
+        }
+        result => result.map(Some),
+    }
+}
+
+fn admit_job(control_module: &ControlModule, job: Job) {
+    if !control_module.processor_claimed() {
+
+        // Add the job entrance event to be immediately treated
+
+        control_module.schedule_now("Ingresso de job".to_string(), Metadata::JobEntrance(job));
+
+    } else {
+        control_module.add_SEQ(job);
     }
 }
 
@@ -98,29 +277,30 @@ struct JobEntrance {
 }
 
 impl JobEntrance {
-    fn unwrap_metadata(&self) -> Option<Job> {
+    fn unwrap_metadata(&self) -> Result<Job, SimulationError> {
         match &self.metadata {
-            Metadata::JobEntrance(Job) => Some(Job.clone()),
-            _ => None,
+            Metadata::JobEntrance(job) => Ok(job.clone()),
+            _ => Err(SimulationError::UnexpectedMetadata("JobEntrance", self.metadata.clone())),
         }
     }
 }
 
 impl Runnable for JobEntrance {
-    fn run(&self, control_module: &ControlModule) {
+    fn run(&self, control_module: &ControlModule) -> Result<(), SimulationError> {
         println!("JobEntrance esta rodando!");
 
-        if let Some(mut job) = self.unwrap_metadata() {
+        let mut job = self.unwrap_metadata()?;
 
-            control_module.remove_SEQ();
+        control_module.load_spooled_deck(job.id);
 
-            job.state = 2;
+        control_module.set_job_state(&mut job, 2);
 
-            // Add the request memory event to be immediately treated
+        // Add the request memory event to be immediately treated
+
+        control_module.schedule_now("Requisicao de memoria de job".to_string(), Metadata::RequestMemory(job));
 
-            control_module.add_event(0, "Requisicao de memoria de job".to_string(), Metadata::RequestMemory(job.clone()));
-        }
         println!("JobEntrance terminou!");
+        Ok(())
     }
 }
 
@@ -129,48 +309,55 @@ struct RequestMemory {
 }
 
 impl RequestMemory {
-    fn unwrap_metadata(&self) -> Option<Job> {
+    fn unwrap_metadata(&self) -> Result<Job, SimulationError> {
         match &self.metadata {
-            Metadata::RequestMemory(Job) => Some(Job.clone()),
-            _ => None,
+            Metadata::RequestMemory(job) => Ok(job.clone()),
+            _ => Err(SimulationError::UnexpectedMetadata("RequestMemory", self.metadata.clone())),
         }
     }
 }
 
 impl Runnable for RequestMemory {
-    fn run(&self, control_module: &ControlModule) {
+    fn run(&self, control_module: &ControlModule) -> Result<(), SimulationError> {
         println!("RequestMemory esta rodando!");
         println!("\n");
         // Verifica inicialmente se há algum job na fila de
         // alocação de memória. Se não ocorrer, e houver área livre,
         // alocam-se para o job X a quantidade de memória solicitada,
-        // e atualiza a quantidade de memória restante. Supostamente
-        // o loader deve carregar o código do Job na área alocada e
-        // o job passa para estado 3 (pronto para execução) e passa
-        // a aguardar na fila do processador. A seguir, é inserido o
-        // evento dependente “Requisição de Processador Job X” para
-        // tratamento imediato.
+        // e atualiza a quantidade de memória restante. O loader
+        // carrega o código do Job na área alocada, relocando os seus
+        // endereços, e o job passa para estado 3 (pronto para
+        // execução) e passa a aguardar na fila do processador. A
+        // seguir, é inserido o evento dependente “Requisição de
+        // Processador Job X”, para tratamento imediato ou, se houve
+        // carga, para o fim dela.
     
-        if let Some(mut job) = self.unwrap_metadata() {
-            let num = job.memory_size;
-            let result = control_module.alloc_memory(job.clone(), num);
-            match result {
-                Ok(value) => {
-                    job.state = 3;
-
-                    // Add the request cpu event to be immediately treated
-
-                    control_module.add_event(0, "Requisicao de processador de job".to_string(), Metadata::RequestCPU(job.clone()));
-                }
-                Err(value) => {
-                    control_module.add_MAQ(job);
-                    println!("Job adicionado a fila de alocacao de memoria. O sistema tentara alocar a memoria novamente apos a saida de algum job do sistema.");
-                    let new_job = control_module.remove_CAQ().unwrap();
-                    control_module.add_event(0, "Requisicao de processador de job".to_string(), Metadata::RequestCPU(new_job.clone()));
+        let mut job = self.unwrap_metadata()?;
+        let num = job.memory_size;
+        let result = control_module.alloc_memory(job.clone(), num);
+        match result {
+            Ok(_) => {
+                control_module.set_job_state(&mut job, 3);
+
+                // A carga leva um tempo proporcional ao tamanho do
+                // programa: o job so pede o processador no fim dela,
+                // mesmo que outro job esteja executando enquanto isso
+
+                let load_time = control_module.load_program(job.id).unwrap_or(SimDuration::ZERO);
+                control_module.schedule_after(load_time, "Requisicao de processador de job".to_string(), Metadata::RequestCPU(job));
+            }
+            Err(_) => {
+                control_module.add_MAQ(job);
+                println!("Job adicionado a fila de alocacao de memoria. O sistema tentara alocar a memoria novamente apos a saida de algum job do sistema.");
+                if control_module.eq_is_empty() {
+                    if let Some(new_job) = control_module.remove_CAQ() {
+                        control_module.schedule_now("Requisicao de processador de job".to_string(), Metadata::RequestCPU(new_job));
+                    }
                 }
             }
         }
         println!("RequestMemory terminou!");
+        Ok(())
     }
 }
 
@@ -179,16 +366,16 @@ struct RequestCPU {
 }
 
 impl RequestCPU {
-    fn unwrap_metadata(&self) -> Option<Job> {
+    fn unwrap_metadata(&self) -> Result<Job, SimulationError> {
         match &self.metadata {
-            Metadata::RequestCPU(Job) => Some(Job.clone()),
-            _ => None,
+            Metadata::RequestCPU(job) => Ok(job.clone()),
+            _ => Err(SimulationError::UnexpectedMetadata("RequestCPU", self.metadata.clone())),
         }
     }
 }
 
 impl Runnable for RequestCPU {
-    fn run(&self, control_module: &ControlModule) {
+    fn run(&self, control_module: &ControlModule) -> Result<(), SimulationError> {
         println!("RequestCPU esta rodando!");
         println!("\n");
         // Insere o job X na fila de execução, para ser
@@ -199,117 +386,175 @@ impl Runnable for RequestCPU {
         // base no instante corrente e o tempo de execução
         // previsto para o job X.
         
-        let time_slice = 10;
+        let time_slice = control_module.time_slice();
         let current_timestep = control_module.get_current_timestep();
         
-        if let Some(mut job) = self.unwrap_metadata() {    
-            job.state = 4;
-            if !control_module.job_exists_in_table(job.id) {
+        let mut job = self.unwrap_metadata()?;
+
+        // Se outro job ficou com o processador enquanto este era
+        // carregado, ele aguarda na fila de alocacao de processador
+
+        if !control_module.eq_is_empty() {
+            println!("Processador ocupado: job {} aguarda na fila de alocacao de processador", job.id);
+            control_module.add_CAQ(job);
+            println!("RequestCPU terminou!");
+            return Ok(());
+        }
+        control_module.set_job_state(&mut job, 4);
+        if let Some(registers) = control_module.registers(job.id) {
+            println!("Registradores carregados: base={}, limite={}", registers.base, registers.limit);
+        }
+        let dispatched = job.clone();
+        let (burst_end, state_end) = match control_module.get_time_remaining(job.id) {
+            None => {
                 let job_cpu_time = job.cpu_time;
-                
+
                 control_module.add_to_job_table(job.id, job_cpu_time);
 
                 println!("Timestep atual: {}", current_timestep);
                 println!("Tempo de cpu do job: {}", job_cpu_time);
 
-                let state_end = current_timestep + time_slice;
+                // A job whose processing time is shorter than the time
+                // slice ends before it; under FCFS the slice never does
+                let state_end = current_timestep + job_cpu_time.min(time_slice);
                 println!("Fim do uso da cpu: {}", state_end);
                 println!("\n");
                 control_module.add_EQ(job.clone());
 
                 // Add the PauseJob event to be treated after job_cpu_time
-                // timesteps.
-
-                if job_cpu_time > time_slice {
-                    control_module.add_event(state_end, "Pause job".to_string(), Metadata::PauseJob(job));
+                // timesteps. A job whose profile has an I/O burst within
+                // this time slice leaves the processor for it instead.
+
+                if let Some(cpu_until_io) = control_module.cpu_until_io(job.id, time_slice) {
+                    let state_end = current_timestep + cpu_until_io;
+                    (control_module.schedule_at(state_end, "Inicio de E/S de job".to_string(), Metadata::StartIo(job, cpu_until_io)), state_end)
+                } else if job_cpu_time > time_slice {
+                    (control_module.schedule_at(state_end, "Pause job".to_string(), Metadata::PauseJob(job)), state_end)
                 } else {
-                    control_module.add_event(state_end, "Fim de processamento de job".to_string(), Metadata::EndProcess(job));
+                    (control_module.schedule_at(state_end, "Fim de processamento de job".to_string(), Metadata::EndProcess(job)), state_end)
                 }
-            } else {
+            }
+            Some(time_remaining) => {
                 // dai significa que estamos pedindo cpu de novo
                 // apos o job ja ter executado por um timeslice
-               
-                let time_remaining = control_module.get_time_remaining(job.id);
+
                 println!("Processing time remaining for job {}: {}", job.id, time_remaining);
                 control_module.add_EQ(job.clone());
-                if time_remaining <= time_slice {
+                if let Some(cpu_until_io) = control_module.cpu_until_io(job.id, time_slice) {
+                    let state_end = current_timestep + cpu_until_io;
+                    (control_module.schedule_at(state_end, "Inicio de E/S de job".to_string(), Metadata::StartIo(job, cpu_until_io)), state_end)
+                } else if time_remaining <= time_slice {
                     let state_end = current_timestep + time_remaining;
-                    control_module.add_event(state_end, "Fim de processamento de job".to_string(), Metadata::EndProcess(job));
+                    (control_module.schedule_at(state_end, "Fim de processamento de job".to_string(), Metadata::EndProcess(job)), state_end)
                 } else {
                     let state_end = current_timestep + time_slice;
-                    control_module.add_event(state_end, "Pause job".to_string(), Metadata::PauseJob(job));
+                    (control_module.schedule_at(state_end, "Pause job".to_string(), Metadata::PauseJob(job)), state_end)
                 }
             }
-            println!("EventList: {:?}", control_module.shared_state.get_event_list());
-        }
+        };
+        execute_program(control_module, dispatched, burst_end, state_end);
+        println!("EventList: {:?}", control_module.shared_state.get_event_list());
         println!("\n");
         println!("RequestCPU terminou!");
+        Ok(())
     }
 }
 
+// Um job com programa recebe o processador pelo tempo calculado
+// acima. Uma copia da maquina mostra onde a execucao vai parar: se o
+// programa terminar, violar a protecao de memoria ou encontrar uma
+// palavra que nao e instrucao antes do fim desse tempo, o evento do
+// fim e trocado pelo fim do processamento, pela falha de protecao ou
+// pelo cancelamento do job, no instante em que a execucao para. As
+// instrucoes so sao executadas quando o job deixa o processador.
+fn execute_program(control_module: &ControlModule, job: Job, burst_end: EventHandle, state_end: SimTime) {
+    let (now, job_id) = (control_module.get_current_timestep(), job.id);
+    let Some(execution) = control_module.start_program_slice(job_id, state_end - now) else {
+        return;
+    };
+    let stop_time = now + execution.elapsed;
+    let (name, metadata) = match execution.stop {
+        Stop::Quantum => return,
+        Stop::Exited => {
+            println!("Programa do job {} vai terminar no instante {}", job.id, stop_time);
+            ("Fim de processamento de job", Metadata::EndProcess(job))
+        }
+        Stop::Fault(fault) => {
+            println!("Programa do job {} vai parar no instante {}: {}", job.id, stop_time, fault);
+            ("Falha de protecao de job", Metadata::ProtectionFault(job.id, fault.address))
+        }
+        Stop::IllegalInstruction(address) => {
+            println!("Programa do job {} vai parar no instante {}: instrucao invalida no endereco {}", job.id, stop_time, address);
+            ("Cancelamento de job", Metadata::KillJob(job.id))
+        }
+    };
+    control_module.cancel_event(burst_end);
+    let stop_event = control_module.schedule_at(stop_time, name.to_string(), metadata);
+    control_module.set_program_stop(job_id, stop_event);
+}
 
 struct PauseJob {
     metadata: Metadata,
 }
 
 impl PauseJob {
-    fn unwrap_metadata(&self) -> Option<Job> {
+    fn unwrap_metadata(&self) -> Result<Job, SimulationError> {
         match &self.metadata {
-            Metadata::PauseJob(Job) => Some(Job.clone()),
-            _ => None,
+            Metadata::PauseJob(job) => Ok(job.clone()),
+            _ => Err(SimulationError::UnexpectedMetadata("PauseJob", self.metadata.clone())),
         }
     }
 }
 
 impl Runnable for PauseJob {
-    fn run(&self, control_module: &ControlModule) {
+    fn run(&self, control_module: &ControlModule) -> Result<(), SimulationError> {
         println!("PauseJob esta rodando!");
         println!("\n");
 
-        if let Some(mut job) = self.unwrap_metadata() {
-            let time_slice = 10;
-            control_module.update_job_table(job.id, time_slice);
-            job.state = 3;
+        let job = self.unwrap_metadata()?;
+        control_module.finish_program_slice(job.id);
+        let time_slice = control_module.time_slice();
+        control_module.update_job_table(job.id, time_slice);
 
-            // Checa se tem job na system entry queue e se o
-            // numero de jobs atualmente rodando eh menor do que
-            // um certo numero
-            
-            let max_jobs = 2;
-            if !control_module.table_is_full(max_jobs) && !control_module.seq_is_empty() {
+        // Checa se tem job na system entry queue e se o
+        // numero de jobs atualmente rodando eh menor do que
+        // um certo numero
 
-                let mut old_job = control_module.remove_EQ().unwrap();
-                println!("Removido job {} da fila de execucao", old_job.id);
-                control_module.add_CAQ(old_job);
+        let max_jobs = control_module.multiprogramming_level();
+        if !control_module.table_is_full(max_jobs) && !control_module.seq_is_empty() {
 
-                println!("Fila de Entrada no Sistema: {:?}", control_module.shared_state.get_system_entry_queue());
-                let mut new_job = control_module.remove_SEQ().unwrap();
+            let old_job = control_module.remove_EQ().ok_or(SimulationError::EmptyQueue("ExecQueue"))?;
+            println!("Removido job {} da fila de execucao", old_job.id);
+            control_module.add_CAQ(old_job);
 
-                new_job.state = 2;
-                
-                // Add the request memory event to be immediately treated
+            println!("Fila de Entrada no Sistema: {:?}", control_module.shared_state.get_system_entry_queue());
+            let mut new_job = control_module.remove_SEQ().ok_or(SimulationError::EmptyQueue("SystemEntryQueue"))?;
 
-                control_module.add_event(0, "Requisicao de memoria de job".to_string(), Metadata::RequestMemory(new_job.clone()));
-            } else {
-                // Manda mais um requestCPU pro proximo job da fila de cpu,
-                // podendo evidentemente ser o mesmo job
+            control_module.set_job_state(&mut new_job, 2);
 
-                println!("Fila de execucao: {:?}", control_module.shared_state.get_exec_queue());
-                let mut new_job = control_module.remove_EQ().unwrap();
-                println!("Fila de execucao apos remocao: {:?}", control_module.shared_state.get_exec_queue());
-                println!("\n");
-                control_module.add_CAQ(new_job);
+            // Add the request memory event to be immediately treated
 
-                println!("Fila de Alocacao de Processador: {:?}", control_module.shared_state.get_cpu_alloc_queue());
-                let actual_new_job = control_module.remove_CAQ().unwrap();
-                println!("Fila de Alocacao de Processador apos remocao: {:?}", control_module.shared_state.get_cpu_alloc_queue());
+            control_module.schedule_now("Requisicao de memoria de job".to_string(), Metadata::RequestMemory(new_job));
+        } else {
+            // Manda mais um requestCPU pro proximo job da fila de cpu,
+            // podendo evidentemente ser o mesmo job
 
-                control_module.add_event(0, "Requisicao de processador de job".to_string(), Metadata::RequestCPU(actual_new_job));
+            println!("Fila de execucao: {:?}", control_module.shared_state.get_exec_queue());
+            let new_job = control_module.remove_EQ().ok_or(SimulationError::EmptyQueue("ExecQueue"))?;
+            println!("Fila de execucao apos remocao: {:?}", control_module.shared_state.get_exec_queue());
+            println!("\n");
+            control_module.add_CAQ(new_job);
+
+            println!("Fila de Alocacao de Processador: {:?}", control_module.shared_state.get_cpu_alloc_queue());
+            let actual_new_job = control_module.remove_CAQ().ok_or(SimulationError::EmptyQueue("CPUAllocQueue"))?;
+            println!("Fila de Alocacao de Processador apos remocao: {:?}", control_module.shared_state.get_cpu_alloc_queue());
+
+            control_module.schedule_now("Requisicao de processador de job".to_string(), Metadata::RequestCPU(actual_new_job));
 
-            }
         }
         println!("\n");
         println!("PauseJob terminou!");
+        Ok(())
     }
 }
 
@@ -319,16 +564,16 @@ struct EndProcess {
 }
 
 impl EndProcess {
-    fn unwrap_metadata(&self) -> Option<Job> {
+    fn unwrap_metadata(&self) -> Result<Job, SimulationError> {
         match &self.metadata {
-            Metadata::EndProcess(Job) => Some(Job.clone()),
-            _ => None,
+            Metadata::EndProcess(job) => Ok(job.clone()),
+            _ => Err(SimulationError::UnexpectedMetadata("EndProcess", self.metadata.clone())),
         }
     }
 }
 
 impl Runnable for EndProcess {
-    fn run(&self, control_module: &ControlModule) {
+    fn run(&self, control_module: &ControlModule) -> Result<(), SimulationError> {
         println!("EndProcess esta rodando!");
         // se houver um job na fila de ingresso ao sistema,
         // ele deve ser retirado dessa fila. O tratamento
@@ -338,12 +583,14 @@ impl Runnable for EndProcess {
         // na fila de eventos, para o Job a ser terminado, o
         // evento dependente de liberação de processador.
 
-        if let Some(job) = self.unwrap_metadata() {
-            control_module.delete_job_table(job.id);
-            control_module.remove_EQ();
-            control_module.add_event(0, "Liberacao de processador job".to_string(), Metadata::FreeCPU(job));
-        }
+        let job = self.unwrap_metadata()?;
+        control_module.finish_program_slice(job.id);
+        control_module.delete_job_table(job.id);
+        control_module.remove_EQ();
+        control_module.schedule_now("Liberacao de processador job".to_string(), Metadata::FreeCPU(job));
+
         println!("EndProcess terminou!");
+        Ok(())
     }
 }
 
@@ -352,24 +599,25 @@ struct FreeCPU {
 }
 
 impl FreeCPU {
-    fn unwrap_metadata(&self) -> Option<Job> {
+    fn unwrap_metadata(&self) -> Result<Job, SimulationError> {
         match &self.metadata {
-            Metadata::FreeCPU(Job) => Some(Job.clone()),
-            _ => None,
+            Metadata::FreeCPU(job) => Ok(job.clone()),
+            _ => Err(SimulationError::UnexpectedMetadata("FreeCPU", self.metadata.clone())),
         }
     }
 }
 
 impl Runnable for FreeCPU {
-    fn run(&self, control_module: &ControlModule) {
+    fn run(&self, control_module: &ControlModule) -> Result<(), SimulationError> {
         println!("FreeCPU esta rodando!");
 
-        if let Some(mut job) = self.unwrap_metadata() {
-            job.state = 5;
-            control_module.remove_EQ();
-            control_module.add_event(0, "Liberacao de memoria job".to_string(), Metadata::FreeMemory(job));
-        }
+        let mut job = self.unwrap_metadata()?;
+        control_module.set_job_state(&mut job, 5);
+        control_module.remove_EQ();
+        control_module.schedule_now("Liberacao de memoria job".to_string(), Metadata::FreeMemory(job));
+
         println!("FreeCPU terminou!");
+        Ok(())
     }
 }
 
@@ -378,25 +626,25 @@ struct FreeMemory {
 }
 
 impl FreeMemory {
-    fn unwrap_metadata(&self) -> Option<Job> {
+    fn unwrap_metadata(&self) -> Result<Job, SimulationError> {
         match &self.metadata {
-            Metadata::FreeMemory(Job) => Some(Job.clone()),
-            _ => None,
+            Metadata::FreeMemory(job) => Ok(job.clone()),
+            _ => Err(SimulationError::UnexpectedMetadata("FreeMemory", self.metadata.clone())),
         }
     }
 }
 
 impl Runnable for FreeMemory {
-    fn run(&self, control_module: &ControlModule) {
+    fn run(&self, control_module: &ControlModule) -> Result<(), SimulationError> {
         println!("FreeMemory esta rodando!");
 
-        if let Some(mut job) = self.unwrap_metadata() {
-            job.state = 6;
-            let num = job.memory_size;
-            control_module.dealloc_memory(job.clone());
-            control_module.add_event(0, "Saida do sistema job".to_string(), Metadata::ExitSystem(job));
-        }
+        let mut job = self.unwrap_metadata()?;
+        control_module.set_job_state(&mut job, 6);
+        control_module.dealloc_memory(job.clone());
+        control_module.schedule_now("Saida do sistema job".to_string(), Metadata::ExitSystem(job));
+
         println!("FreeMemory terminou!");
+        Ok(())
     }
 }
 
@@ -405,40 +653,1127 @@ struct ExitSystem {
 }
 
 impl ExitSystem {
-    fn unwrap_metadata(&self) -> Option<Job> {
+    fn unwrap_metadata(&self) -> Result<Job, SimulationError> {
         match &self.metadata {
-            Metadata::ExitSystem(Job) => Some(Job.clone()),
-            _ => None,
+            Metadata::ExitSystem(job) => Ok(job.clone()),
+            _ => Err(SimulationError::UnexpectedMetadata("ExitSystem", self.metadata.clone())),
         }
     }
 }
 
 impl Runnable for ExitSystem {
-    fn run(&self, control_module: &ControlModule) {
+    fn run(&self, control_module: &ControlModule) -> Result<(), SimulationError> {
         println!("ExitSystem esta rodando!");
 
+        let exiting_job = self.unwrap_metadata()?;
+        control_module.record_job_exit(exiting_job.id);
+        control_module.release_all_resources(exiting_job.id);
+        control_module.release_synchronization(exiting_job.id);
+        control_module.release_channels(exiting_job.id);
+        if let Some(end) = control_module.spool_output(&exiting_job) {
+            println!("Saida do job {} sera impressa ate o instante {}", exiting_job.id, end);
+        }
+
         if !control_module.maq_is_empty() {
             println!("Fila de alocacao de memoria contem algum job: inserindo evento dependente de requisicao de memoria ao sistema.");
 
-            let mut job = control_module.remove_MAQ().unwrap();
+            let job = control_module.remove_MAQ().ok_or(SimulationError::EmptyQueue("MemoryAllocQueue"))?;
 
             // Add the request memory event to be immediately treated
 
-            control_module.add_event(0, "Requisicao de memoria de job".to_string(), Metadata::RequestMemory(job.clone()));
+            control_module.schedule_now("Requisicao de memoria de job".to_string(), Metadata::RequestMemory(job));
         } else if !control_module.seq_is_empty() {
             println!("Fila de ingresso ao sistema contem algum evento: inserindo evento dependente de requisicao de memoria ao sistema.");
 
-            let mut job = control_module.remove_SEQ().unwrap();
+            let mut job = control_module.remove_SEQ().ok_or(SimulationError::EmptyQueue("SystemEntryQueue"))?;
 
-            job.state = 2;
+            control_module.set_job_state(&mut job, 2);
 
             // Add the request memory event to be immediately treated
 
-            control_module.add_event(0, "Requisicao de memoria de job".to_string(), Metadata::RequestMemory(job.clone()));
+            control_module.schedule_now("Requisicao de memoria de job".to_string(), Metadata::RequestMemory(job));
         } else {
             println!("Fila de ingresso ao sistema nao contem nenhum evento e fila de alocacao de memoria nao contem nenhum job.")
         }
+
+        // Se nenhum job admitido agora vai ocupar o processador, ele
+        // passa ao primeiro job da fila de alocacao de processador.
+
+        if !control_module.processor_claimed() && !control_module.caq_is_empty() {
+            let next_job = control_module.remove_CAQ().ok_or(SimulationError::EmptyQueue("CPUAllocQueue"))?;
+
+            // Add the request cpu event to be immediately treated
+
+            control_module.schedule_now("Requisicao de processador de job".to_string(), Metadata::RequestCPU(next_job));
+        }
         println!("ExitSystem terminou!");
+        Ok(())
+    }
+}
+
+struct KillJob {
+    metadata: Metadata,
+}
+
+impl KillJob {
+    fn unwrap_metadata(&self) -> Result<i32, SimulationError> {
+        match &self.metadata {
+            Metadata::KillJob(job_id) => Ok(*job_id),
+            _ => Err(SimulationError::UnexpectedMetadata("KillJob", self.metadata.clone())),
+        }
+    }
+}
+
+impl Runnable for KillJob {
+    fn run(&self, control_module: &ControlModule) -> Result<(), SimulationError> {
+        println!("KillJob esta rodando!");
+        // Retira o job X de qualquer fila em que esteja, cancela
+        // os eventos pendentes que se referem a ele (fim de time
+        // slice, fim de processamento, ...), descarta as suas
+        // requisicoes de disco e libera a sua memoria e o seu deck no
+        // spool. Um acesso ao disco ja em curso termina normalmente.
+        // Se o job estava em execucao, o processador e entregue ao
+        // proximo job da fila de alocacao de processador; a memoria
+        // liberada e oferecida aos jobs que aguardam, como na saida
+        // do sistema.
+
+        let job_id = self.unwrap_metadata()?;
+        control_module.finish_program_slice(job_id);
+
+        let removed = control_module.remove_from_queues(job_id);
+        let cancelled = control_module.cancel_job_events(job_id);
+        for (queue, _) in &removed {
+            println!("Job {} removido da fila {}", job_id, queue);
+        }
+        for event in &cancelled {
+            println!("Evento cancelado: {:?}", event);
+        }
+        control_module.release_all_resources(job_id);
+        control_module.release_synchronization(job_id);
+        control_module.release_channels(job_id);
+        let disk_requests = control_module.cancel_disk_requests(job_id);
+        if disk_requests > 0 {
+            println!("{} requisicoes de disco do job {} descartadas", disk_requests, job_id);
+        }
+        if control_module.load_spooled_deck(job_id) {
+            println!("Deck do job {} descartado do spool", job_id);
+        }
+
+        let job = removed
+            .iter()
+            .map(|(_, job)| job.clone())
+            .chain(cancelled.iter().filter_map(|event| event.metadata.job().cloned()))
+            .next();
+
+        let Some(job) = job else {
+            if cancelled.is_empty() {
+                println!("Job {} nao esta no sistema: nada a cancelar.", job_id);
+            } else {
+                println!("Chegada do job {} cancelada.", job_id);
+                control_module.record_job_kill(job_id);
+            }
+            println!("KillJob terminou!");
+            return Ok(());
+        };
+
+        let was_running = removed.iter().any(|(queue, _)| *queue == "ExecQueue");
+
+        control_module.delete_job_table(job_id);
+        let freed_memory = control_module.dealloc_memory(job);
+        control_module.record_job_kill(job_id);
+
+        if was_running && !control_module.caq_is_empty() {
+            let next_job = control_module.remove_CAQ().ok_or(SimulationError::EmptyQueue("CPUAllocQueue"))?;
+
+            // Add the request cpu event to be immediately treated
+
+            control_module.schedule_now("Requisicao de processador de job".to_string(), Metadata::RequestCPU(next_job));
+        }
+
+        if freed_memory == 0 {
+            println!("KillJob terminou!");
+            return Ok(());
+        }
+
+        if !control_module.maq_is_empty() {
+            let next_job = control_module.remove_MAQ().ok_or(SimulationError::EmptyQueue("MemoryAllocQueue"))?;
+
+            // Add the request memory event to be immediately treated
+
+            control_module.schedule_now("Requisicao de memoria de job".to_string(), Metadata::RequestMemory(next_job));
+        } else if !control_module.seq_is_empty() {
+            let mut next_job = control_module.remove_SEQ().ok_or(SimulationError::EmptyQueue("SystemEntryQueue"))?;
+
+            control_module.set_job_state(&mut next_job, 2);
+
+            // Add the request memory event to be immediately treated
+
+            control_module.schedule_now("Requisicao de memoria de job".to_string(), Metadata::RequestMemory(next_job));
+        }
+        println!("KillJob terminou!");
+        Ok(())
+    }
+}
+
+struct DeclareResources {
+    metadata: Metadata,
+}
+
+impl DeclareResources {
+    fn unwrap_metadata(&self) -> Result<(i32, Vec<i32>), SimulationError> {
+        match &self.metadata {
+            Metadata::DeclareResources(job_id, claim) => Ok((*job_id, claim.clone())),
+            _ => Err(SimulationError::UnexpectedMetadata("DeclareResources", self.metadata.clone())),
+        }
+    }
+}
+
+impl Runnable for DeclareResources {
+    fn run(&self, control_module: &ControlModule) -> Result<(), SimulationError> {
+        println!("DeclareResources esta rodando!");
+        // O job X informa a quantidade maxima de cada tipo de
+        // recurso que podera usar durante sua execucao. Essa
+        // declaracao e usada pelo algoritmo do banqueiro.
+
+        let (job_id, claim) = self.unwrap_metadata()?;
+        println!("Job {} declara uso maximo de recursos: {:?}", job_id, claim);
+        control_module.declare_resources(job_id, claim)?;
+
+        println!("DeclareResources terminou!");
+        Ok(())
+    }
+}
+
+struct RequestResources {
+    metadata: Metadata,
+}
+
+impl RequestResources {
+    fn unwrap_metadata(&self) -> Result<(i32, Vec<i32>), SimulationError> {
+        match &self.metadata {
+            Metadata::RequestResources(job_id, request) => Ok((*job_id, request.clone())),
+            _ => Err(SimulationError::UnexpectedMetadata("RequestResources", self.metadata.clone())),
+        }
+    }
+}
+
+impl Runnable for RequestResources {
+    fn run(&self, control_module: &ControlModule) -> Result<(), SimulationError> {
+        println!("RequestResources esta rodando!");
+        // Se houver unidades livres e o estado resultante for
+        // seguro, os recursos sao alocados ao job X. Caso
+        // contrario, a requisicao fica pendente e o job X e
+        // bloqueado ate que outro job libere recursos.
+
+        let (job_id, request) = self.unwrap_metadata()?;
+        let result = control_module.request_resources(job_id, request.clone());
+        let Some(outcome) = refuse_if_not_running(control_module, result)? else {
+            println!("RequestResources terminou!");
+            return Ok(());
+        };
+        println!("Requisicao de recursos {:?} do job {}: {:?}", request, job_id, outcome);
+        control_module.record_trace(format!("Resultado da requisicao de recursos do job {}", job_id), format!("{:?}", outcome));
+
+        println!("RequestResources terminou!");
+        Ok(())
+    }
+}
+
+struct ReleaseResources {
+    metadata: Metadata,
+}
+
+impl ReleaseResources {
+    fn unwrap_metadata(&self) -> Result<(i32, Vec<i32>), SimulationError> {
+        match &self.metadata {
+            Metadata::ReleaseResources(job_id, release) => Ok((*job_id, release.clone())),
+            _ => Err(SimulationError::UnexpectedMetadata("ReleaseResources", self.metadata.clone())),
+        }
+    }
+}
+
+impl Runnable for ReleaseResources {
+    fn run(&self, control_module: &ControlModule) -> Result<(), SimulationError> {
+        println!("ReleaseResources esta rodando!");
+        // Devolve as unidades ao sistema e tenta atender as
+        // requisicoes pendentes, desbloqueando os jobs atendidos.
+
+        let (job_id, release) = self.unwrap_metadata()?;
+        println!("Job {} libera recursos: {:?}", job_id, release);
+        control_module.release_resources(job_id, release)?;
+
+        println!("ReleaseResources terminou!");
+        Ok(())
+    }
+}
+
+struct SetPriority {
+    metadata: Metadata,
+}
+
+impl SetPriority {
+    fn unwrap_metadata(&self) -> Result<(i32, i32), SimulationError> {
+        match &self.metadata {
+            Metadata::SetPriority(job_id, priority) => Ok((*job_id, *priority)),
+            _ => Err(SimulationError::UnexpectedMetadata("SetPriority", self.metadata.clone())),
+        }
+    }
+}
+
+impl Runnable for SetPriority {
+    fn run(&self, control_module: &ControlModule) -> Result<(), SimulationError> {
+        println!("SetPriority esta rodando!");
+
+        let (job_id, priority) = self.unwrap_metadata()?;
+        println!("Job {} passa a ter prioridade {}", job_id, priority);
+        control_module.set_job_priority(job_id, priority);
+
+        println!("SetPriority terminou!");
+        Ok(())
+    }
+}
+
+struct MemoryAccess {
+    metadata: Metadata,
+}
+
+impl MemoryAccess {
+    fn unwrap_metadata(&self) -> Result<(i32, i32), SimulationError> {
+        match &self.metadata {
+            Metadata::MemoryAccess(job_id, address) => Ok((*job_id, *address)),
+            _ => Err(SimulationError::UnexpectedMetadata("MemoryAccess", self.metadata.clone())),
+        }
+    }
+}
+
+impl Runnable for MemoryAccess {
+    fn run(&self, control_module: &ControlModule) -> Result<(), SimulationError> {
+        println!("MemoryAccess esta rodando!");
+        // O endereco logico gerado pelo job X e somado ao registrador
+        // base de seu segmento. Se passar do registrador limite, o
+        // acesso invadiria a memoria de outro job: e gerada uma falha
+        // de protecao para tratamento imediato.
+
+        let (job_id, address) = self.unwrap_metadata()?;
+        match control_module.translate_address(job_id, address) {
+            Some(Ok(physical)) => println!("Job {}: endereco logico {} -> endereco fisico {}", job_id, address, physical),
+            Some(Err(fault)) => {
+                println!("Job {}: {}", job_id, fault);
+                control_module.schedule_now("Falha de protecao de job".to_string(), Metadata::ProtectionFault(job_id, address));
+            }
+            None => println!("Job {} nao esta na memoria: acesso ignorado.", job_id),
+        }
+
+        println!("MemoryAccess terminou!");
+        Ok(())
+    }
+}
+
+struct ProtectionFault {
+    metadata: Metadata,
+}
+
+impl ProtectionFault {
+    fn unwrap_metadata(&self) -> Result<(i32, i32), SimulationError> {
+        match &self.metadata {
+            Metadata::ProtectionFault(job_id, address) => Ok((*job_id, *address)),
+            _ => Err(SimulationError::UnexpectedMetadata("ProtectionFault", self.metadata.clone())),
+        }
+    }
+}
+
+impl Runnable for ProtectionFault {
+    fn run(&self, control_module: &ControlModule) -> Result<(), SimulationError> {
+        println!("ProtectionFault esta rodando!");
+        // O sistema operacional assume o controle e encerra o job
+        // que tentou acessar fora de seu segmento.
+
+        let (job_id, address) = self.unwrap_metadata()?;
+        println!("Falha de protecao no endereco {}: job {} sera cancelado", address, job_id);
+        control_module.kill_job(job_id);
+
+        println!("ProtectionFault terminou!");
+        Ok(())
+    }
+}
+
+struct DetectDeadlock;
+
+impl Runnable for DetectDeadlock {
+    fn run(&self, control_module: &ControlModule) -> Result<(), SimulationError> {
+        println!("DetectDeadlock esta rodando!");
+        // Procura jobs cujas requisicoes de recursos nunca poderao
+        // ser atendidas. Se houver deadlock, escolhe como vitima o
+        // job de menor prioridade (o mais novo, em caso de empate)
+        // e o cancela ou toma seus recursos, conforme a estrategia
+        // de recuperacao. A deteccao e reagendada enquanto algum
+        // job no sistema usar recursos, ou algum job ainda for
+        // declara-los, e o estado ainda puder mudar: houve
+        // recuperacao ou ha outros eventos.
+
+        let ResourcePolicy::Detection { period, recovery } = control_module.resource_policy() else {
+            println!("DetectDeadlock terminou!");
+            return Ok(());
+        };
+
+        let (deadlocked, wait_for_graph) = control_module.detect_deadlock();
+        let victim = deadlocked
+            .iter()
+            .copied()
+            .min_by_key(|&job_id| (control_module.get_job_priority(job_id), -job_id));
+        let recovered = victim.is_some();
+        if let Some(victim) = victim {
+            println!("Deadlock entre os jobs {:?}; grafo de espera: {:?}", deadlocked, wait_for_graph);
+            control_module.record_trace(
+                "Deadlock detectado".to_string(),
+                format!("jobs {:?}, espera por {:?}", deadlocked, wait_for_graph),
+            );
+
+            control_module.record_deadlock(victim);
+
+            match recovery {
+                RecoveryStrategy::KillLowestPriority => {
+                    println!("Job {} escolhido como vitima: sera cancelado.", victim);
+                    control_module.record_trace("Recuperacao de deadlock".to_string(), format!("cancelamento do job {}", victim));
+                    control_module.kill_job(victim);
+                }
+                RecoveryStrategy::PreemptResources => {
+                    let taken = control_module.preempt_resources(victim);
+                    println!("Job {} escolhido como vitima: recursos {:?} retomados.", victim, taken);
+                    control_module.record_trace(
+                        "Recuperacao de deadlock".to_string(),
+                        format!("recursos {:?} retomados do job {}", taken, victim),
+                    );
+                }
+            }
+        } else {
+            println!("Nenhum deadlock encontrado.");
+        }
+
+        if control_module.resources_in_use() && (recovered || control_module.has_pending_events()) {
+            control_module.schedule_after(period.duration(), "Deteccao de deadlock".to_string(), Metadata::DetectDeadlock);
+        }
+
+        println!("DetectDeadlock terminou!");
+        Ok(())
+    }
+}
+
+struct SemaphoreWait {
+    metadata: Metadata,
+}
+
+impl SemaphoreWait {
+    fn unwrap_metadata(&self) -> Result<(i32, String), SimulationError> {
+        match &self.metadata {
+            Metadata::SemaphoreWait(job_id, name) => Ok((*job_id, name.clone())),
+            _ => Err(SimulationError::UnexpectedMetadata("SemaphoreWait", self.metadata.clone())),
+        }
     }
 }
 
+impl Runnable for SemaphoreWait {
+    fn run(&self, control_module: &ControlModule) -> Result<(), SimulationError> {
+        println!("SemaphoreWait esta rodando!");
+        // Se o semaforo (ou mutex) estiver disponivel, o job X o
+        // obtem e continua executando. Caso contrario, o job X entra
+        // na fila do semaforo e e bloqueado ate receber um signal.
+
+        let (job_id, name) = self.unwrap_metadata()?;
+        match refuse_if_not_running(control_module, control_module.semaphore_wait(job_id, &name))? {
+            Some(true) => println!("Job {} obteve {}", job_id, name),
+            Some(false) => println!("Job {} aguarda {}", job_id, name),
+            None => {}
+        }
+
+        println!("SemaphoreWait terminou!");
+        Ok(())
+    }
+}
+
+struct SemaphoreSignal {
+    metadata: Metadata,
+}
+
+impl SemaphoreSignal {
+    fn unwrap_metadata(&self) -> Result<(i32, String), SimulationError> {
+        match &self.metadata {
+            Metadata::SemaphoreSignal(job_id, name) => Ok((*job_id, name.clone())),
+            _ => Err(SimulationError::UnexpectedMetadata("SemaphoreSignal", self.metadata.clone())),
+        }
+    }
+}
+
+impl Runnable for SemaphoreSignal {
+    fn run(&self, control_module: &ControlModule) -> Result<(), SimulationError> {
+        println!("SemaphoreSignal esta rodando!");
+        // Libera o semaforo (ou mutex). Se houver job aguardando, o
+        // mais antigo e acordado pelo evento dependente "Despertar
+        // de job", para tratamento imediato.
+
+        let (job_id, name) = self.unwrap_metadata()?;
+        if let Some(woken_job) = control_module.semaphore_signal(job_id, &name)? {
+            println!("Signal do job {} em {} acorda o job {}", job_id, name, woken_job);
+        }
+
+        println!("SemaphoreSignal terminou!");
+        Ok(())
+    }
+}
+
+struct WakeJob {
+    metadata: Metadata,
+}
+
+impl WakeJob {
+    fn unwrap_metadata(&self) -> Result<(i32, String), SimulationError> {
+        match &self.metadata {
+            Metadata::WakeJob(job_id, name) => Ok((*job_id, name.clone())),
+            _ => Err(SimulationError::UnexpectedMetadata("WakeJob", self.metadata.clone())),
+        }
+    }
+}
+
+impl Runnable for WakeJob {
+    fn run(&self, control_module: &ControlModule) -> Result<(), SimulationError> {
+        println!("WakeJob esta rodando!");
+
+        let (job_id, name) = self.unwrap_metadata()?;
+        println!("Job {} recebeu {} e volta a disputar o processador", job_id, name);
+        control_module.unblock_job(job_id);
+
+        println!("WakeJob terminou!");
+        Ok(())
+    }
+}
+
+struct SendMessage {
+    metadata: Metadata,
+}
+
+impl SendMessage {
+    fn unwrap_metadata(&self) -> Result<(i32, String, i32), SimulationError> {
+        match &self.metadata {
+            Metadata::SendMessage(job_id, channel, size) => Ok((*job_id, channel.clone(), *size)),
+            _ => Err(SimulationError::UnexpectedMetadata("SendMessage", self.metadata.clone())),
+        }
+    }
+}
+
+impl Runnable for SendMessage {
+    fn run(&self, control_module: &ControlModule) -> Result<(), SimulationError> {
+        println!("SendMessage esta rodando!");
+        // Se houver espaco no buffer do canal, a mensagem do job X e
+        // colocada nele e fica disponivel apos a latencia do canal
+        // (evento dependente "Entrega de mensagem"). Caso contrario,
+        // o job X e bloqueado ate que um receptor libere espaco.
+
+        let (job_id, channel, size) = self.unwrap_metadata()?;
+        match refuse_if_not_running(control_module, control_module.send_message(job_id, &channel, size))? {
+            Some(true) => println!("Job {} enviou mensagem de tamanho {} pelo canal {}", job_id, size, channel),
+            Some(false) => println!("Job {} aguarda espaco no canal {}", job_id, channel),
+            None => {}
+        }
+
+        println!("SendMessage terminou!");
+        Ok(())
+    }
+}
+
+struct ReceiveMessage {
+    metadata: Metadata,
+}
+
+impl ReceiveMessage {
+    fn unwrap_metadata(&self) -> Result<(i32, String), SimulationError> {
+        match &self.metadata {
+            Metadata::ReceiveMessage(job_id, channel) => Ok((*job_id, channel.clone())),
+            _ => Err(SimulationError::UnexpectedMetadata("ReceiveMessage", self.metadata.clone())),
+        }
+    }
+}
+
+impl Runnable for ReceiveMessage {
+    fn run(&self, control_module: &ControlModule) -> Result<(), SimulationError> {
+        println!("ReceiveMessage esta rodando!");
+        // Se houver mensagem disponivel no canal, o job X a recebe e
+        // os emissores bloqueados podem ocupar o espaco liberado.
+        // Caso contrario, o job X e bloqueado ate a proxima entrega.
+
+        let (job_id, channel) = self.unwrap_metadata()?;
+        match refuse_if_not_running(control_module, control_module.receive_message(job_id, &channel))? {
+            Some(Some(size)) => println!("Job {} recebeu mensagem de tamanho {} pelo canal {}", job_id, size, channel),
+            Some(None) => println!("Job {} aguarda mensagem no canal {}", job_id, channel),
+            None => {}
+        }
+
+        println!("ReceiveMessage terminou!");
+        Ok(())
+    }
+}
+
+struct DeliverMessage {
+    metadata: Metadata,
+}
+
+impl DeliverMessage {
+    fn unwrap_metadata(&self) -> Result<String, SimulationError> {
+        match &self.metadata {
+            Metadata::DeliverMessage(channel) => Ok(channel.clone()),
+            _ => Err(SimulationError::UnexpectedMetadata("DeliverMessage", self.metadata.clone())),
+        }
+    }
+}
+
+impl Runnable for DeliverMessage {
+    fn run(&self, control_module: &ControlModule) -> Result<(), SimulationError> {
+        println!("DeliverMessage esta rodando!");
+        // Passada a latencia do canal, a mensagem fica disponivel e
+        // e entregue ao receptor bloqueado mais antigo, se houver.
+
+        let channel = self.unwrap_metadata()?;
+        for receiver in control_module.deliver_messages(&channel)? {
+            println!("Mensagem do canal {} entregue ao job {}", channel, receiver);
+        }
+
+        println!("DeliverMessage terminou!");
+        Ok(())
+    }
+}
+
+struct FileOperation {
+    metadata: Metadata,
+}
+
+// Operation a job asks the file system for
+enum FileRequest {
+    CreateDirectory(String),
+    Create(String),
+    Write(String, i32),
+    Read(String, i32, i32),
+    Delete(String),
+}
+
+impl FileOperation {
+    fn unwrap_metadata(&self) -> Result<(i32, FileRequest), SimulationError> {
+        match &self.metadata {
+            Metadata::CreateDirectory(job_id, path) => Ok((*job_id, FileRequest::CreateDirectory(path.clone()))),
+            Metadata::CreateFile(job_id, path) => Ok((*job_id, FileRequest::Create(path.clone()))),
+            Metadata::WriteFile(job_id, path, bytes) => Ok((*job_id, FileRequest::Write(path.clone(), *bytes))),
+            Metadata::ReadFile(job_id, path, offset, bytes) => {
+                Ok((*job_id, FileRequest::Read(path.clone(), *offset, *bytes)))
+            }
+            Metadata::DeleteFile(job_id, path) => Ok((*job_id, FileRequest::Delete(path.clone()))),
+            _ => Err(SimulationError::UnexpectedMetadata("FileOperation", self.metadata.clone())),
+        }
+    }
+}
+
+impl Runnable for FileOperation {
+    fn run(&self, control_module: &ControlModule) -> Result<(), SimulationError> {
+        println!("FileOperation esta rodando!");
+        // O job X cria, escreve, le ou remove um arquivo (ou cria um
+        // diretorio). O custo da operacao e o numero de blocos do
+        // disco acessados, que depende da estrategia de alocacao.
+        // Uma operacao que falha e registrada no trace e o job segue.
+
+        let (job_id, request) = self.unwrap_metadata()?;
+        let result = control_module.file_operation(|fs| match &request {
+            FileRequest::CreateDirectory(path) => fs.create_directory(path),
+            FileRequest::Create(path) => fs.create(path),
+            FileRequest::Write(path, bytes) => fs.write(path, *bytes),
+            FileRequest::Read(path, offset, bytes) => fs.read(path, *offset, *bytes),
+            FileRequest::Delete(path) => fs.delete(path),
+        });
+        match result {
+            Ok(cost) => println!("Operacao do job {} acessou {} blocos", job_id, cost),
+            Err(error) => {
+                println!("Operacao do job {} falhou: {}", job_id, error);
+                control_module.record_trace(format!("Falha em operacao de arquivo do job {}", job_id), error.to_string());
+            }
+        }
+
+        println!("FileOperation terminou!");
+        Ok(())
+    }
+}
+
+struct DiskRequest {
+    metadata: Metadata,
+}
+
+impl DiskRequest {
+    fn unwrap_metadata(&self) -> Result<(i32, i32), SimulationError> {
+        match self.metadata {
+            Metadata::DiskRequest(job_id, cylinder) => Ok((job_id, cylinder)),
+            _ => Err(SimulationError::UnexpectedMetadata("DiskRequest", self.metadata.clone())),
+        }
+    }
+}
+
+impl Runnable for DiskRequest {
+    fn run(&self, control_module: &ControlModule) -> Result<(), SimulationError> {
+        println!("DiskRequest esta rodando!");
+        // A requisicao do job X entra na fila do disco. Se o disco
+        // estiver livre, o proximo acesso comeca ao fim do instante
+        // atual (evento dependente "Inicio de acesso ao disco").
+
+        let (job_id, cylinder) = self.unwrap_metadata()?;
+        println!("Job {} requisita o cilindro {}", job_id, cylinder);
+        control_module.request_disk(job_id, cylinder);
+
+        println!("DiskRequest terminou!");
+        Ok(())
+    }
+}
+
+struct StartDiskAccess;
+
+impl Runnable for StartDiskAccess {
+    fn run(&self, control_module: &ControlModule) -> Result<(), SimulationError> {
+        println!("StartDiskAccess esta rodando!");
+        // A politica de escalonamento do disco escolhe a proxima
+        // requisicao; o fim do acesso e agendado apos os tempos de
+        // seek, rotacao e transferencia.
+
+        if let Some(request) = control_module.start_disk_access() {
+            println!("Braco do disco vai ao cilindro {} (job {})", request.cylinder, request.job_id);
+        }
+
+        println!("StartDiskAccess terminou!");
+        Ok(())
+    }
+}
+
+struct EndDiskAccess;
+
+impl Runnable for EndDiskAccess {
+    fn run(&self, control_module: &ControlModule) -> Result<(), SimulationError> {
+        println!("EndDiskAccess esta rodando!");
+
+        if let Some(request) = control_module.end_disk_access() {
+            println!("Acesso ao cilindro {} do job {} concluido", request.cylinder, request.job_id);
+        }
+
+        println!("EndDiskAccess terminou!");
+        Ok(())
+    }
+}
+
+struct StartIo {
+    metadata: Metadata,
+}
+
+impl StartIo {
+    fn unwrap_metadata(&self) -> Result<(Job, SimDuration), SimulationError> {
+        match &self.metadata {
+            Metadata::StartIo(job, cpu_used) => Ok((job.clone(), *cpu_used)),
+            _ => Err(SimulationError::UnexpectedMetadata("StartIo", self.metadata.clone())),
+        }
+    }
+}
+
+impl Runnable for StartIo {
+    fn run(&self, control_module: &ControlModule) -> Result<(), SimulationError> {
+        println!("StartIo esta rodando!");
+        // O job X terminou sua rajada de processamento e deixa o
+        // processador, que passa ao proximo job da fila. O job fica
+        // bloqueado ate o fim da rajada de E/S no dispositivo, que
+        // atende um job por vez (evento "Fim de E/S de job").
+
+        let (job, cpu_used) = self.unwrap_metadata()?;
+        let job_id = job.id;
+        control_module.finish_program_slice(job_id);
+        if let Some((device, end)) = control_module.start_io(job, cpu_used) {
+            println!("Job {} usa {} ate o instante {}", job_id, device, end);
+        }
+
+        println!("StartIo terminou!");
+        Ok(())
+    }
+}
+
+struct EndIo {
+    metadata: Metadata,
+}
+
+impl EndIo {
+    fn unwrap_metadata(&self) -> Result<Job, SimulationError> {
+        match &self.metadata {
+            Metadata::EndIo(job) => Ok(job.clone()),
+            _ => Err(SimulationError::UnexpectedMetadata("EndIo", self.metadata.clone())),
+        }
+    }
+}
+
+impl Runnable for EndIo {
+    fn run(&self, control_module: &ControlModule) -> Result<(), SimulationError> {
+        println!("EndIo esta rodando!");
+
+        let job = self.unwrap_metadata()?;
+        println!("Job {} terminou sua rajada de E/S e volta a disputar o processador", job.id);
+        control_module.unblock_job(job.id);
+
+        println!("EndIo terminou!");
+        Ok(())
+    }
+}
+
+struct EndCardRead {
+    metadata: Metadata,
+}
+
+impl EndCardRead {
+    fn unwrap_metadata(&self) -> Result<Job, SimulationError> {
+        match &self.metadata {
+            Metadata::EndCardRead(job) => Ok(job.clone()),
+            _ => Err(SimulationError::UnexpectedMetadata("EndCardRead", self.metadata.clone())),
+        }
+    }
+}
+
+impl Runnable for EndCardRead {
+    fn run(&self, control_module: &ControlModule) -> Result<(), SimulationError> {
+        println!("EndCardRead esta rodando!");
+        // O deck do job X esta todo no spool: o job pode ingressar no
+        // sistema, como se tivesse acabado de chegar.
+
+        let job = self.unwrap_metadata()?;
+        admit_job(control_module, job);
+
+        println!("EndCardRead terminou!");
+        Ok(())
+    }
+}
+
+struct EndPrint {
+    metadata: Metadata,
+}
+
+impl EndPrint {
+    fn unwrap_metadata(&self) -> Result<Job, SimulationError> {
+        match &self.metadata {
+            Metadata::EndPrint(job) => Ok(job.clone()),
+            _ => Err(SimulationError::UnexpectedMetadata("EndPrint", self.metadata.clone())),
+        }
+    }
+}
+
+impl Runnable for EndPrint {
+    fn run(&self, control_module: &ControlModule) -> Result<(), SimulationError> {
+        println!("EndPrint esta rodando!");
+
+        let job = self.unwrap_metadata()?;
+        println!("Saida do job {} impressa", job.id);
+        control_module.end_print(job.id);
+
+        println!("EndPrint terminou!");
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ExternalClock;
+    use crate::populate_list::{populate_config, populate_list};
+    use crate::event_list::EventList;
+    use crate::machine::Instruction;
+    use crate::spooling::SpoolingConfig;
+    use crate::system_abstractions::{SchedulerPolicy, SharedState, SystemConfig};
+    use crate::time::SimTime;
+    use std::collections::BTreeMap;
+    use std::sync::{Arc, Mutex};
+
+    // Builds a control module for the events under the config, driven
+    // by an external clock the test moves forward
+    fn scenario(config: &SystemConfig, event_list: EventList) -> (ControlModule, ExternalClock) {
+        let clock = ExternalClock::new();
+        let control_module = ControlModule::with_clock(SharedState::from_config(event_list, config), Box::new(clock.clone()));
+        (control_module, clock)
+    }
+
+    // Handles every event up to the given instant
+    fn run_until(control_module: &mut ControlModule, clock: &ExternalClock, until: i32) {
+        clock.advance_to(until);
+        control_module.run(&create_event_to_routine()).unwrap();
+    }
+
+    fn run_scenario(config: &SystemConfig, event_list: EventList, until: i32) -> ControlModule {
+        let (mut control_module, clock) = scenario(config, event_list);
+        run_until(&mut control_module, &clock, until);
+        control_module
+    }
+
+    // Instants at which the trace recorded an event of the given name
+    fn traced_at(control_module: &ControlModule, name: &str) -> Vec<SimTime> {
+        let trace = control_module.shared_state.get_trace();
+        let trace = trace.lock().unwrap();
+        trace.entries().iter().filter(|entry| entry.name == name).map(|entry| entry.time).collect()
+    }
+
+    // Counts the jobs it is handed instead of admitting them
+    struct CountArrival(Arc<Mutex<Vec<i32>>>, Metadata);
+
+    impl Runnable for CountArrival {
+        fn run(&self, _control_module: &ControlModule) -> Result<(), SimulationError> {
+            if let Some(job_id) = self.1.job_id() {
+                self.0.lock().unwrap().push(job_id);
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_registered_routine_overrides_built_in() {
+        let (mut control_module, clock) = scenario(&populate_config(2), populate_list(2));
+        let arrivals = Arc::new(Mutex::new(Vec::new()));
+        let seen = arrivals.clone();
+        control_module.register_routine("JobArrival", Box::new(move |metadata| {
+            Box::new(CountArrival(seen.clone(), metadata.clone())) as Box<dyn Runnable>
+        }));
+        run_until(&mut control_module, &clock, 1000);
+
+        // No job was admitted, so none ever used the processor
+        let mut arrivals = arrivals.lock().unwrap().clone();
+        arrivals.sort();
+        assert_eq!(arrivals, vec![1, 2, 3, 4, 5]);
+        let statistics = control_module.shared_state.get_statistics();
+        assert_eq!(statistics.lock().unwrap().jobs_completed(), 0);
+    }
+
+    // Records the fields of the custom events it handles
+    struct Audit(Arc<Mutex<Vec<String>>>, Metadata);
+
+    impl Runnable for Audit {
+        fn run(&self, _control_module: &ControlModule) -> Result<(), SimulationError> {
+            match &self.1 {
+                Metadata::Custom(kind, fields) if kind == "auditoria" => {
+                    self.0.lock().unwrap().push(fields["nota"].clone());
+                    Ok(())
+                }
+                metadata => Err(SimulationError::UnexpectedMetadata("Audit", metadata.clone())),
+            }
+        }
+    }
+
+    #[test]
+    fn test_registered_routine_handles_a_new_event_kind() {
+        let mut event_list = EventList::new();
+        for (time, note) in [(3, "primeira"), (8, "segunda")] {
+            let fields = BTreeMap::from([(String::from("nota"), String::from(note))]);
+            event_list.push(time, String::from("Auditoria"), Metadata::Custom(String::from("auditoria"), fields));
+        }
+        let (mut control_module, clock) = scenario(&SystemConfig::default(), event_list);
+        let notes = Arc::new(Mutex::new(Vec::new()));
+        let seen = notes.clone();
+        control_module.register_routine("Audit", Box::new(move |metadata| {
+            Box::new(Audit(seen.clone(), metadata.clone())) as Box<dyn Runnable>
+        }));
+        let mut event_to_routine = create_event_to_routine();
+        event_to_routine.insert("Auditoria", "Audit");
+        clock.advance_to(10);
+        control_module.run(&event_to_routine).unwrap();
+
+        assert_eq!(*notes.lock().unwrap(), vec![String::from("primeira"), String::from("segunda")]);
+        assert_eq!(traced_at(&control_module, "Auditoria"), vec![SimTime::from(3), SimTime::from(8)]);
+    }
+
+    #[test]
+    fn test_kill_during_disk_access_keeps_the_disk_going() {
+        let mut event_list = EventList::new();
+        event_list.push(0, String::from("Requisicao de disco"), Metadata::DiskRequest(1, 100));
+        event_list.push(1, String::from("Requisicao de disco"), Metadata::DiskRequest(1, 150));
+        event_list.push(2, String::from("Requisicao de disco"), Metadata::DiskRequest(2, 50));
+        event_list.push(5, String::from("Cancelamento de job"), Metadata::KillJob(1));
+        let control_module = run_scenario(&populate_config(2), event_list, 1000);
+
+        // The access in course at the kill ends, the other request of
+        // job 1 is dropped and the arm goes on to job 2
+        let statistics = control_module.shared_state.get_statistics();
+        let statistics = statistics.lock().unwrap();
+        assert_eq!(statistics.disk_requests, 2);
+        assert_eq!(statistics.disk_seek_distance, 100 + 50);
+        assert!(!control_module.shared_state.get_disk().lock().unwrap().is_busy());
+    }
+
+    #[test]
+    fn test_kill_while_deck_is_read_frees_the_spool() {
+        let mut event_list = EventList::new();
+        event_list.push(0, String::from("Chegada de job"), Metadata::JobArrival(1, 10, SimDuration::from(10)));
+        event_list.push(0, String::from("Chegada de job"), Metadata::JobArrival(2, 10, SimDuration::from(10)));
+        event_list.push(5, String::from("Cancelamento de job"), Metadata::KillJob(2));
+        let config = SystemConfig { spooling: Some(SpoolingConfig::default()), ..SystemConfig::default() };
+        let control_module = run_scenario(&config, event_list, 1000);
+
+        // Job 2 was killed while its deck was still being read
+        let spool = control_module.shared_state.get_spool();
+        let spool = spool.lock().unwrap();
+        assert_eq!(spool.as_ref().unwrap().used(), 0);
+        let statistics = control_module.shared_state.get_statistics();
+        assert_eq!(statistics.lock().unwrap().jobs_completed(), 1);
+    }
+
+    #[test]
+    fn test_request_that_cannot_block_is_refused() {
+        let mut event_list = EventList::new();
+        event_list.push(0, String::from("Declaracao de recursos de job"), Metadata::DeclareResources(1, vec![1]));
+        event_list.push(0, String::from("Declaracao de recursos de job"), Metadata::DeclareResources(2, vec![1]));
+        event_list.push(1, String::from("Chegada de job"), Metadata::JobArrival(1, 10, SimDuration::from(50)));
+        event_list.push(2, String::from("Requisicao de recursos de job"), Metadata::RequestResources(1, vec![1]));
+        event_list.push(3, String::from("Chegada de job"), Metadata::JobArrival(2, 10, SimDuration::from(50)));
+        event_list.push(4, String::from("Requisicao de recursos de job"), Metadata::RequestResources(2, vec![1]));
+        let config = SystemConfig { resources: vec![(String::from("Fita"), 1)], ..SystemConfig::default() };
+        let control_module = run_scenario(&config, event_list, 5);
+
+        // Job 2 is still in the system entry queue: its request is not
+        // kept, so it cannot be granted later to a job that never blocked
+        assert_eq!(traced_at(&control_module, "Pedido recusado do job 2"), vec![SimTime::from(4)]);
+        assert!(control_module.shared_state.get_resource_manager().lock().unwrap().waiting().is_empty());
+    }
+
+    #[test]
+    fn test_first_slice_ends_with_a_short_job() {
+        for scheduler in [SchedulerPolicy::RoundRobin, SchedulerPolicy::Fcfs] {
+            let mut event_list = EventList::new();
+            event_list.push(0, String::from("Chegada de job"), Metadata::JobArrival(1, 10, SimDuration::from(4)));
+            let config = SystemConfig { scheduler, ..SystemConfig::default() };
+            let control_module = run_scenario(&config, event_list, 1000);
+
+            assert_eq!(traced_at(&control_module, "Fim de processamento de job"), vec![SimTime::from(4)]);
+        }
+    }
+
+    #[test]
+    fn test_preempted_running_job_starts_over() {
+        let mut event_list = EventList::new();
+        event_list.push(0, String::from("Declaracao de recursos de job"), Metadata::DeclareResources(1, vec![1]));
+        event_list.push(0, String::from("Chegada de job"), Metadata::JobArrival(1, 10, SimDuration::from(30)));
+        event_list.push(2, String::from("Requisicao de recursos de job"), Metadata::RequestResources(1, vec![1]));
+        let config = SystemConfig {
+            resources: vec![(String::from("Fita"), 1)],
+            resource_policy: ResourcePolicy::detection(SimDuration::from(100), RecoveryStrategy::PreemptResources).unwrap(),
+            ..SystemConfig::default()
+        };
+        let control_module = run_scenario(&config, event_list, 15);
+
+        // The running job leaves the processor, gets its tape back at
+        // once and owes its whole processing time again
+        assert_eq!(control_module.preempt_resources(1), vec![1]);
+        assert!(!control_module.is_blocked(1));
+        assert_eq!(control_module.get_time_remaining(1), Some(SimDuration::from(30)));
+        assert!(control_module.shared_state.get_resource_manager().lock().unwrap().waiting().is_empty());
+    }
+
+    #[test]
+    fn test_program_runs_no_further_than_the_simulation() {
+        let source = "
+            loop: SYS 1
+                  JMP loop
+        ";
+        let mut event_list = EventList::new();
+        event_list.push(0, String::from("Chegada de job com programa"), Metadata::ProgramJobArrival(1, 20, SimDuration::from(100), String::from(source)));
+        event_list.push(33, String::from("Cancelamento de job"), Metadata::KillJob(1));
+        let (mut control_module, clock) = scenario(&SystemConfig::default(), event_list);
+        let written = |control_module: &ControlModule| {
+            let statistics = control_module.shared_state.get_statistics();
+            let statistics = statistics.lock().unwrap();
+            (statistics.instructions_executed, statistics.program_output.iter().map(|&(time, _, _)| time).max())
+        };
+
+        // Nothing runs ahead of the current instant
+        run_until(&mut control_module, &clock, 25);
+        let (executed, last_output) = written(&control_module);
+        assert!(executed <= 25);
+        assert!(last_output.is_some_and(|time| time <= SimTime::from(25)));
+
+        // A slice cut short runs only up to the kill
+        run_until(&mut control_module, &clock, 200);
+        let (executed, last_output) = written(&control_module);
+        assert!(executed > 25 && executed <= 33);
+        assert!(last_output.is_some_and(|time| time <= SimTime::from(33)));
+    }
+
+    #[test]
+    fn test_blocked_program_keeps_the_work_it_has_left() {
+        let source = "
+            LI    R0, 1
+            LI    R0, 2
+            LI    R0, 3
+            LI    R0, 4
+            STORE R0, 200
+        ";
+        let mut event_list = EventList::new();
+        event_list.push(0, String::from("Chegada de job com programa"), Metadata::ProgramJobArrival(1, 20, SimDuration::from(100), String::from(source)));
+        event_list.push(7, String::from("Wait em semaforo de job"), Metadata::SemaphoreWait(1, String::from("s")));
+        let config = SystemConfig { semaphores: vec![(String::from("s"), 0)], ..SystemConfig::default() };
+        let control_module = run_scenario(&config, event_list, 50);
+
+        // The program got the processor at 5, once loaded, and ran two
+        // instructions; the fault it would hit at 10 never happens
+        assert!(control_module.is_blocked(1));
+        assert!(traced_at(&control_module, "Falha de protecao de job").is_empty());
+        assert_eq!(control_module.get_time_remaining(1), Some(SimDuration::from(98)));
+    }
+
+    #[test]
+    fn test_program_loaded_while_processor_is_busy() {
+        let source = "
+                   LOAD  R0, value
+                   SYS   0
+            value: WORD  7
+        ";
+        let mut event_list = EventList::new();
+        event_list.push(0, String::from("Chegada de job"), Metadata::JobArrival(1, 10, SimDuration::from(100)));
+        event_list.push(1, String::from("Chegada de job"), Metadata::JobArrival(2, 100, SimDuration::from(100)));
+        event_list.push(2, String::from("Chegada de job com programa"), Metadata::ProgramJobArrival(3, 20, SimDuration::from(10), String::from(source)));
+        event_list.push(25, String::from("Cancelamento de job"), Metadata::KillJob(2));
+        let control_module = run_scenario(&SystemConfig::default(), event_list, 29);
+
+        // Killing job 2, which waited for the processor, lets job 3 in
+        // at address 10 while job 1 runs. The address field of the
+        // LOAD points into the segment.
+        assert_eq!(control_module.registers(3).map(|registers| registers.base), Some(10));
+        let memory = control_module.shared_state.get_memory();
+        let memory = memory.lock().unwrap();
+        assert_eq!(memory.read(10).and_then(Instruction::decode), Some(Instruction::Load(0, 12)));
+        assert_eq!(memory.read(12), Some(7));
+
+        // The three words take three timesteps to load, and job 3 then
+        // waits for job 1 to give the processor back
+        assert_eq!(traced_at(&control_module, "Carga de programa"), vec![SimTime::from(25)]);
+        let trace = control_module.shared_state.get_trace();
+        let requested: Vec<SimTime> = trace
+            .lock()
+            .unwrap()
+            .entries()
+            .iter()
+            .filter(|entry| entry.name == "Requisicao de processador de job" && entry.detail.contains("id: 3"))
+            .map(|entry| entry.time)
+            .collect();
+        assert_eq!(requested, vec![SimTime::from(28)]);
+        assert!(control_module.shared_state.get_cpu_alloc_queue().lock().unwrap().contains(3));
+    }
+
+    #[test]
+    fn test_schedule_now_runs_after_events_already_due() {
+        let mut event_list = EventList::new();
+        event_list.push(20, String::from("Chegada de job"), Metadata::JobArrival(1, 10, SimDuration::from(10)));
+        event_list.push(20, String::from("Chegada de job"), Metadata::JobArrival(2, 10, SimDuration::from(10)));
+        let (mut control_module, _) = scenario(&populate_config(2), event_list);
+        control_module.update_current_timestep(SimTime::from(20));
+
+        control_module.schedule_now(String::from("Chegada de job"), Metadata::JobArrival(3, 10, SimDuration::from(10)));
+        control_module.schedule_at(SimTime::from(5), String::from("Chegada de job"), Metadata::JobArrival(4, 10, SimDuration::from(10)));
+        control_module.schedule_after(SimDuration::from(1), String::from("Chegada de job"), Metadata::JobArrival(5, 10, SimDuration::from(10)));
+
+        // A past instant is taken as now, behind the follow-up already scheduled
+        let event_list = control_module.shared_state.get_event_list();
+        let order: Vec<(SimTime, Option<i32>)> =
+            event_list.lock().unwrap().iter().map(|event| (event.time, event.metadata.job_id())).collect();
+        let ids: Vec<Option<i32>> = order.iter().map(|&(_, id)| id).collect();
+        assert_eq!(ids, vec![Some(1), Some(2), Some(3), Some(4), Some(5)]);
+        assert_eq!(order[3].0, SimTime::from(20));
+        assert_eq!(order[4].0, SimTime::from(21));
+    }
+}
@@ -0,0 +1,92 @@
+// In this file it is defined the error type returned by the
+// routines, and the failure report produced by the event loop
+// when a routine cannot handle an event.
+use crate::event_list::Metadata;
+use crate::communication::ChannelError;
+use crate::resources::ResourceError;
+use crate::time::SimTime;
+use crate::synchronization::SyncError;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SimulationError {
+    // A routine tried to remove a job from a queue that was empty.
+    // The field holds the name of the queue.
+    EmptyQueue(&'static str),
+    // A routine received metadata that does not belong to it,
+    // e.g. an event name mapped to the wrong routine.
+    UnexpectedMetadata(&'static str, Metadata),
+    // The resource manager rejected a declaration, request or release
+    Resource(ResourceError),
+    // A wait or signal on a semaphore or mutex was invalid
+    Synchronization(SyncError),
+    // A send or receive named a missing channel or an oversized message
+    Communication(ChannelError),
+    // A request had to block a job that is neither running nor
+    // waiting for the processor; the request is not kept
+    JobNotRunning(i32),
+}
+
+impl fmt::Display for SimulationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SimulationError::EmptyQueue(queue) => {
+                write!(f, "tentativa de remover job da fila vazia {}", queue)
+            }
+            SimulationError::UnexpectedMetadata(routine, metadata) => {
+                write!(f, "rotina {} recebeu metadata inesperado {:?}", routine, metadata)
+            }
+            SimulationError::Resource(error) => write!(f, "{}", error),
+            SimulationError::Synchronization(error) => write!(f, "{}", error),
+            SimulationError::Communication(error) => write!(f, "{}", error),
+            SimulationError::JobNotRunning(job_id) => {
+                write!(f, "job {} nao esta em execucao nem aguardando o processador", job_id)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SimulationError {}
+
+impl From<ResourceError> for SimulationError {
+    fn from(error: ResourceError) -> Self {
+        SimulationError::Resource(error)
+    }
+}
+
+impl From<SyncError> for SimulationError {
+    fn from(error: SyncError) -> Self {
+        SimulationError::Synchronization(error)
+    }
+}
+
+impl From<ChannelError> for SimulationError {
+    fn from(error: ChannelError) -> Self {
+        SimulationError::Communication(error)
+    }
+}
+
+// Report of an event whose routine failed, with a snapshot of
+// the system state taken right after the failure.
+#[derive(Debug)]
+pub struct EventFailure {
+    pub time: SimTime,
+    pub name: String,
+    pub metadata: Metadata,
+    pub state: String,
+    pub error: SimulationError,
+}
+
+impl fmt::Display for EventFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Falha ao tratar o evento \"{}\" (instante {}): {}", self.name, self.time, self.error)?;
+        writeln!(f, "Event metadata: {:?}", self.metadata)?;
+        write!(f, "Estado do sistema:\n{}", self.state)
+    }
+}
+
+impl std::error::Error for EventFailure {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
@@ -0,0 +1,138 @@
+// In this file it is implemented the spooling of the batch system.
+// Before a job enters the system its card deck is read by the card
+// reader into a spool area on disk, and after it leaves the system
+// its output is moved from the spool area to the line printer. Both
+// devices serve one job at a time, in order of arrival.
+use crate::time::{SimDuration, SimTime};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpoolingConfig {
+    // Time the card reader takes per card
+    pub card_time: SimDuration,
+    // Time the printer takes per line
+    pub line_time: SimDuration,
+    // Cards and lines of the jobs not listed in decks
+    pub default_cards: i32,
+    pub default_lines: i32,
+    // Job, cards in its deck and lines of its output
+    pub decks: Vec<(i32, i32, i32)>,
+}
+
+impl Default for SpoolingConfig {
+    fn default() -> Self {
+        SpoolingConfig {
+            card_time: SimDuration::from(1),
+            line_time: SimDuration::from(1),
+            default_cards: 10,
+            default_lines: 10,
+            decks: Vec::new(),
+        }
+    }
+}
+
+impl SpoolingConfig {
+    // Cards and lines of the job
+    pub fn deck(&self, job_id: i32) -> (i32, i32) {
+        self.decks
+            .iter()
+            .find(|(id, _, _)| *id == job_id)
+            .map_or((self.default_cards, self.default_lines), |&(_, cards, lines)| (cards, lines))
+    }
+}
+
+#[derive(Debug)]
+pub struct Spool {
+    config: SpoolingConfig,
+    reader_free_at: SimTime,
+    printer_free_at: SimTime,
+    // Cards and lines currently held in the spool area
+    used: i32,
+    peak: i32,
+    // Jobs whose deck is in the spool area
+    held_decks: Vec<i32>,
+}
+
+impl Spool {
+    pub fn new(config: SpoolingConfig) -> Self {
+        Spool {
+            config,
+            reader_free_at: SimTime::ZERO,
+            printer_free_at: SimTime::ZERO,
+            used: 0,
+            peak: 0,
+            held_decks: Vec::new(),
+        }
+    }
+
+    pub fn used(&self) -> i32 {
+        self.used
+    }
+
+    pub fn peak(&self) -> i32 {
+        self.peak
+    }
+
+    fn store(&mut self, amount: i32) {
+        self.used += amount;
+        self.peak = self.peak.max(self.used);
+    }
+
+    // Queues the deck of the job on the card reader. Returns the
+    // number of cards and the instant the whole deck is in the spool.
+    pub fn read_deck(&mut self, job_id: i32, now: SimTime) -> (i32, SimTime) {
+        let (cards, _) = self.config.deck(job_id);
+        self.reader_free_at = self.reader_free_at.max(now) + self.config.card_time * cards.into();
+        self.store(cards);
+        self.held_decks.push(job_id);
+        (cards, self.reader_free_at)
+    }
+
+    // The deck leaves the spool area once the job enters the system,
+    // or is killed before that. Returns false if the deck was not in
+    // the spool area.
+    pub fn load_deck(&mut self, job_id: i32) -> bool {
+        let Some(position) = self.held_decks.iter().position(|&id| id == job_id) else {
+            return false;
+        };
+        self.held_decks.remove(position);
+        self.used -= self.config.deck(job_id).0;
+        true
+    }
+
+    // Spools the output of the job and queues it on the printer.
+    // Returns the number of lines and the instant printing ends.
+    pub fn print(&mut self, job_id: i32, now: SimTime) -> (i32, SimTime) {
+        let (_, lines) = self.config.deck(job_id);
+        self.printer_free_at = self.printer_free_at.max(now) + self.config.line_time * lines.into();
+        self.store(lines);
+        (lines, self.printer_free_at)
+    }
+
+    pub fn end_print(&mut self, job_id: i32) {
+        self.used -= self.config.deck(job_id).1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_devices_serve_one_job_at_a_time() {
+        let t = SimTime::from;
+        let config = SpoolingConfig { card_time: SimDuration::from(2), decks: vec![(2, 5, 30)], ..SpoolingConfig::default() };
+        let mut spool = Spool::new(config);
+
+        assert_eq!(spool.read_deck(1, t(0)), (10, t(20)));
+        // The reader is busy with job 1 until instant 20
+        assert_eq!(spool.read_deck(2, t(4)), (5, t(30)));
+        assert_eq!(spool.used(), 15);
+
+        assert!(spool.load_deck(1));
+        assert!(!spool.load_deck(1));
+        assert_eq!(spool.print(2, t(40)), (30, t(70)));
+        assert_eq!(spool.print(1, t(45)), (10, t(80)));
+        spool.end_print(2);
+        assert_eq!((spool.used(), spool.peak()), (15, 45));
+    }
+}
@@ -0,0 +1,561 @@
+// In this file it is implemented the statistics collected
+// during a simulation run, reported once the event list is
+// exhausted.
+use crate::disk::DiskSchedulingPolicy;
+use crate::file_system::{AllocationStrategy, FragmentationReport};
+use crate::machine::{Execution, Stop};
+use crate::protection::ProtectionFault;
+use crate::resources::RequestOutcome;
+use crate::system_abstractions::MemoryUsage;
+use crate::time::{SimDuration, SimTime};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::{self, Write};
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct JobStatistics {
+    pub arrival: Option<SimTime>,
+    pub exit: Option<SimTime>,
+    pub killed: Option<SimTime>,
+    // Total time spent in the blocked queue
+    pub blocked_time: SimDuration,
+    blocked_since: Option<SimTime>,
+}
+
+impl JobStatistics {
+    pub fn turnaround(&self) -> Option<SimDuration> {
+        match (self.arrival, self.exit) {
+            (Some(arrival), Some(exit)) => Some(exit - arrival),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ChannelStatistics {
+    pub messages_sent: i32,
+    pub messages_received: i32,
+    pub bytes_received: i32,
+    // Sum of the time between send and receive of every message
+    pub total_latency: SimDuration,
+    pub sender_blocked_time: SimDuration,
+    pub receiver_blocked_time: SimDuration,
+    first_send: Option<SimTime>,
+    last_receive: Option<SimTime>,
+}
+
+impl ChannelStatistics {
+    // Messages received per timestep, from the first send to the
+    // last receive
+    pub fn throughput(&self) -> Option<f64> {
+        match (self.first_send, self.last_receive) {
+            (Some(first), Some(last)) if last > first => Some(self.messages_received as f64 / (last - first).ticks() as f64),
+            _ => None,
+        }
+    }
+
+    pub fn mean_latency(&self) -> Option<f64> {
+        if self.messages_received == 0 {
+            None
+        } else {
+            Some(self.total_latency.ticks() as f64 / self.messages_received as f64)
+        }
+    }
+}
+
+pub const MEMORY_SERIES_HEADER: &str =
+    "instante,memoria_usada,memoria_livre,utilizacao,buracos,maior_buraco,fragmentacao_externa,fragmentacao_interna,tamanhos_dos_buracos";
+
+#[derive(Debug, Default)]
+pub struct Statistics {
+    pub events_processed: i32,
+    pub final_timestep: SimTime,
+    pub resource_requests: i32,
+    pub resource_delays_unavailable: i32,
+    pub resource_delays_unsafe: i32,
+    pub deadlocks_detected: i32,
+    pub deadlock_victims: Vec<i32>,
+    pub sync_waits: i32,
+    pub sync_blocking_waits: i32,
+    pub sync_signals: i32,
+    pub file_operations: i32,
+    pub file_operations_failed: i32,
+    // Disk blocks read or written by the file operations
+    pub file_access_cost: i32,
+    // Allocation strategy and fragmentation after the last file
+    // operation
+    pub file_system: Option<(AllocationStrategy, FragmentationReport)>,
+    pub io_bursts: i32,
+    // Time from the start of each I/O burst to its end, waiting for
+    // the device included
+    pub io_time: SimDuration,
+    pub decks_rejected: i32,
+    pub jobs_spooled: i32,
+    pub cards_read: i32,
+    // Time from each arrival to the end of the reading of its deck
+    pub card_read_time: SimDuration,
+    pub jobs_printed: i32,
+    pub lines_printed: i32,
+    // Time from each exit to the end of the printing of its output
+    pub print_time: SimDuration,
+    pub spool_peak: i32,
+    pub disk_requests: i32,
+    // Cylinders the disk arm moved
+    pub disk_seek_distance: i32,
+    // Sum of the time from each disk request to the end of its access
+    pub disk_response_time: SimDuration,
+    pub disk_policy: Option<DiskSchedulingPolicy>,
+    pub memory_accesses: i32,
+    // Job and fault of every access outside the segment of the job
+    pub protection_faults: Vec<(i32, ProtectionFault)>,
+    pub instructions_executed: i64,
+    // Instant, job and value of everything the programs wrote
+    pub program_output: Vec<(SimTime, i32, i32)>,
+    // Occupation of the memory after every allocation and
+    // deallocation, in time order
+    memory_usage: Vec<(SimTime, MemoryUsage)>,
+    jobs: BTreeMap<i32, JobStatistics>,
+    channels: BTreeMap<String, ChannelStatistics>,
+}
+
+impl Statistics {
+    pub fn new() -> Self {
+        Statistics {
+            events_processed: 0,
+            final_timestep: SimTime::ZERO,
+            resource_requests: 0,
+            resource_delays_unavailable: 0,
+            resource_delays_unsafe: 0,
+            deadlocks_detected: 0,
+            deadlock_victims: Vec::new(),
+            sync_waits: 0,
+            sync_blocking_waits: 0,
+            sync_signals: 0,
+            file_operations: 0,
+            file_operations_failed: 0,
+            file_access_cost: 0,
+            file_system: None,
+            io_bursts: 0,
+            io_time: SimDuration::ZERO,
+            decks_rejected: 0,
+            jobs_spooled: 0,
+            cards_read: 0,
+            card_read_time: SimDuration::ZERO,
+            jobs_printed: 0,
+            lines_printed: 0,
+            print_time: SimDuration::ZERO,
+            spool_peak: 0,
+            disk_requests: 0,
+            disk_seek_distance: 0,
+            disk_response_time: SimDuration::ZERO,
+            disk_policy: None,
+            memory_accesses: 0,
+            protection_faults: Vec::new(),
+            instructions_executed: 0,
+            program_output: Vec::new(),
+            memory_usage: Vec::new(),
+            jobs: BTreeMap::new(),
+            channels: BTreeMap::new(),
+        }
+    }
+
+    pub fn record_event(&mut self, time: SimTime) {
+        self.events_processed += 1;
+        self.final_timestep = time;
+    }
+
+    pub fn record_arrival(&mut self, job_id: i32, time: SimTime) {
+        self.jobs.entry(job_id).or_default().arrival = Some(time);
+    }
+
+    pub fn record_exit(&mut self, job_id: i32, time: SimTime) {
+        self.jobs.entry(job_id).or_default().exit = Some(time);
+    }
+
+    pub fn record_kill(&mut self, job_id: i32, time: SimTime) {
+        self.jobs.entry(job_id).or_default().killed = Some(time);
+    }
+
+    pub fn record_block(&mut self, job_id: i32, time: SimTime) {
+        self.jobs.entry(job_id).or_default().blocked_since = Some(time);
+    }
+
+    pub fn record_unblock(&mut self, job_id: i32, time: SimTime) {
+        let job = self.jobs.entry(job_id).or_default();
+        if let Some(since) = job.blocked_since.take() {
+            job.blocked_time += time - since;
+        }
+    }
+
+    pub fn record_resource_request(&mut self, outcome: &RequestOutcome) {
+        self.resource_requests += 1;
+        match outcome {
+            RequestOutcome::Granted => {}
+            RequestOutcome::Unavailable => self.resource_delays_unavailable += 1,
+            RequestOutcome::Unsafe => self.resource_delays_unsafe += 1,
+        }
+    }
+
+    pub fn record_deadlock(&mut self, victim: i32) {
+        self.deadlocks_detected += 1;
+        self.deadlock_victims.push(victim);
+    }
+
+    pub fn record_sync_wait(&mut self, acquired: bool) {
+        self.sync_waits += 1;
+        if !acquired {
+            self.sync_blocking_waits += 1;
+        }
+    }
+
+    pub fn record_sync_signal(&mut self) {
+        self.sync_signals += 1;
+    }
+
+    pub fn record_channel_send(&mut self, channel: &str, time: SimTime) {
+        let stats = self.channels.entry(channel.to_string()).or_default();
+        stats.messages_sent += 1;
+        stats.first_send.get_or_insert(time);
+    }
+
+    pub fn record_channel_receive(&mut self, channel: &str, size: i32, sent_at: SimTime, time: SimTime) {
+        let stats = self.channels.entry(channel.to_string()).or_default();
+        stats.messages_received += 1;
+        stats.bytes_received += size;
+        stats.total_latency += time - sent_at;
+        stats.last_receive = Some(time);
+    }
+
+    pub fn record_channel_sender_wait(&mut self, channel: &str, time: SimDuration) {
+        self.channels.entry(channel.to_string()).or_default().sender_blocked_time += time;
+    }
+
+    pub fn record_channel_receiver_wait(&mut self, channel: &str, time: SimDuration) {
+        self.channels.entry(channel.to_string()).or_default().receiver_blocked_time += time;
+    }
+
+    // A failed operation has no cost
+    pub fn record_file_operation(&mut self, cost: Option<i32>, strategy: AllocationStrategy, report: FragmentationReport) {
+        self.file_operations += 1;
+        match cost {
+            Some(cost) => self.file_access_cost += cost,
+            None => self.file_operations_failed += 1,
+        }
+        self.file_system = Some((strategy, report));
+    }
+
+    pub fn record_io_burst(&mut self, time: SimDuration) {
+        self.io_bursts += 1;
+        self.io_time += time;
+    }
+
+    pub fn record_deck_rejection(&mut self) {
+        self.decks_rejected += 1;
+    }
+
+    pub fn record_card_read(&mut self, cards: i32, time: SimDuration, spool_peak: i32) {
+        self.jobs_spooled += 1;
+        self.cards_read += cards;
+        self.card_read_time += time;
+        self.spool_peak = spool_peak;
+    }
+
+    pub fn record_print(&mut self, lines: i32, time: SimDuration, spool_peak: i32) {
+        self.jobs_printed += 1;
+        self.lines_printed += lines;
+        self.print_time += time;
+        self.spool_peak = spool_peak;
+    }
+
+    pub fn record_disk_access(&mut self, policy: DiskSchedulingPolicy, distance: i32, response_time: SimDuration) {
+        self.disk_requests += 1;
+        self.disk_seek_distance += distance;
+        self.disk_response_time += response_time;
+        self.disk_policy = Some(policy);
+    }
+
+    pub fn record_memory_access(&mut self, job_id: i32, fault: Option<ProtectionFault>) {
+        self.memory_accesses += 1;
+        if let Some(fault) = fault {
+            self.protection_faults.push((job_id, fault));
+        }
+    }
+
+    // The instructions took one timestep each from the start
+    pub fn record_program_run(&mut self, job_id: i32, start: SimTime, execution: &Execution) {
+        self.instructions_executed += execution.elapsed.ticks();
+        self.memory_accesses += execution.memory_accesses;
+        if let Stop::Fault(fault) = execution.stop {
+            self.protection_faults.push((job_id, fault));
+        }
+        for &(elapsed, value) in &execution.output {
+            self.program_output.push((start + elapsed, job_id, value));
+        }
+    }
+
+    pub fn record_memory_usage(&mut self, time: SimTime, usage: MemoryUsage) {
+        self.memory_usage.push((time, usage));
+    }
+
+    pub fn memory_usage(&self) -> &[(SimTime, MemoryUsage)] {
+        &self.memory_usage
+    }
+
+    // Mean of the value over the whole run, each sample weighted by
+    // the time until the next one. The memory starts empty, so the
+    // time before the first sample counts as zero.
+    fn memory_time_weighted(&self, value: impl Fn(&MemoryUsage) -> f64) -> Option<f64> {
+        let (last_time, _) = self.memory_usage.last()?;
+        let end = self.final_timestep.max(*last_time);
+        if end == SimTime::ZERO {
+            return None;
+        }
+        let total: f64 = self
+            .memory_usage
+            .iter()
+            .zip(self.memory_usage.iter().skip(1).map(|(time, _)| *time).chain([end]))
+            .map(|((time, usage), until)| value(usage) * (until - *time).ticks() as f64)
+            .sum();
+        Some(total / (end - SimTime::ZERO).ticks() as f64)
+    }
+
+    pub fn mean_memory_utilisation(&self) -> Option<f64> {
+        self.memory_time_weighted(MemoryUsage::utilisation)
+    }
+
+    pub fn mean_external_fragmentation(&self) -> Option<f64> {
+        self.memory_time_weighted(MemoryUsage::external_fragmentation)
+    }
+
+    // Writes the memory samples as CSV, one row per allocation or
+    // deallocation; the hole sizes are separated by spaces
+    pub fn write_memory_series<W: Write>(&self, output: &mut W) -> io::Result<()> {
+        writeln!(output, "{}", MEMORY_SERIES_HEADER)?;
+        for (time, usage) in &self.memory_usage {
+            let holes: Vec<String> = usage.holes.iter().map(|size| size.to_string()).collect();
+            writeln!(
+                output,
+                "{},{},{},{:.4},{},{},{:.4},{},{}",
+                time,
+                usage.used,
+                usage.free(),
+                usage.utilisation(),
+                usage.holes.len(),
+                usage.largest_hole(),
+                usage.external_fragmentation(),
+                usage.internal_fragmentation,
+                holes.join(" ")
+            )?;
+        }
+        Ok(())
+    }
+
+    pub fn channel(&self, channel: &str) -> Option<&ChannelStatistics> {
+        self.channels.get(channel)
+    }
+
+    pub fn job(&self, job_id: i32) -> Option<&JobStatistics> {
+        self.jobs.get(&job_id)
+    }
+
+    pub fn jobs_completed(&self) -> i32 {
+        self.jobs.values().filter(|job| job.exit.is_some()).count() as i32
+    }
+
+    pub fn jobs_killed(&self) -> i32 {
+        self.jobs.values().filter(|job| job.killed.is_some()).count() as i32
+    }
+
+    pub fn mean_turnaround(&self) -> Option<f64> {
+        let turnarounds: Vec<SimDuration> = self.jobs.values().filter_map(|job| job.turnaround()).collect();
+        if turnarounds.is_empty() {
+            None
+        } else {
+            Some(turnarounds.iter().copied().sum::<SimDuration>().ticks() as f64 / turnarounds.len() as f64)
+        }
+    }
+}
+
+impl fmt::Display for Statistics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Eventos tratados: {}", self.events_processed)?;
+        writeln!(f, "Instante final: {}", self.final_timestep)?;
+        writeln!(f, "Jobs concluidos: {}", self.jobs_completed())?;
+        writeln!(f, "Jobs cancelados: {}", self.jobs_killed())?;
+        for (id, job) in &self.jobs {
+            let show = |value: Option<i64>| value.map_or("-".to_string(), |v| v.to_string());
+            write!(
+                f,
+                "Job {}: chegada={}, saida={}, turnaround={}",
+                id,
+                show(job.arrival.map(SimTime::ticks)),
+                show(job.exit.map(SimTime::ticks)),
+                show(job.turnaround().map(SimDuration::ticks))
+            )?;
+            if job.blocked_time > SimDuration::ZERO {
+                write!(f, ", bloqueado={}", job.blocked_time)?;
+            }
+            if let Some(killed) = job.killed {
+                write!(f, ", cancelado={}", killed)?;
+            }
+            writeln!(f)?;
+        }
+        if self.resource_requests > 0 {
+            writeln!(
+                f,
+                "Requisicoes de recursos: {} (adiadas por falta: {}, por estado inseguro: {})",
+                self.resource_requests, self.resource_delays_unavailable, self.resource_delays_unsafe
+            )?;
+        }
+        if self.deadlocks_detected > 0 {
+            writeln!(f, "Deadlocks detectados: {} (vitimas: {:?})", self.deadlocks_detected, self.deadlock_victims)?;
+        }
+        if self.sync_waits > 0 || self.sync_signals > 0 {
+            writeln!(
+                f,
+                "Operacoes de sincronizacao: {} waits ({} bloqueantes), {} signals",
+                self.sync_waits, self.sync_blocking_waits, self.sync_signals
+            )?;
+        }
+        for (name, channel) in &self.channels {
+            let show = |value: Option<f64>| value.map_or("-".to_string(), |v| format!("{:.2}", v));
+            writeln!(
+                f,
+                "Canal {}: enviadas={}, recebidas={}, bytes={}, vazao={}, latencia media={}, espera de envio={}, espera de recepcao={}",
+                name,
+                channel.messages_sent,
+                channel.messages_received,
+                channel.bytes_received,
+                show(channel.throughput()),
+                show(channel.mean_latency()),
+                channel.sender_blocked_time,
+                channel.receiver_blocked_time
+            )?;
+        }
+        if let Some((strategy, report)) = self.file_system {
+            let successful = self.file_operations - self.file_operations_failed;
+            let mean_cost = if successful == 0 { 0.0 } else { self.file_access_cost as f64 / successful as f64 };
+            writeln!(
+                f,
+                "Sistema de arquivos ({}): {} operacoes ({} falhas), custo de acesso={} blocos (medio {:.2})",
+                strategy, self.file_operations, self.file_operations_failed, self.file_access_cost, mean_cost
+            )?;
+            writeln!(
+                f,
+                "Fragmentacao: interna={} bytes, externa={:.2}%, blocos livres={}",
+                report.internal,
+                report.external * 100.0,
+                report.free_blocks
+            )?;
+        }
+        if self.io_bursts > 0 {
+            writeln!(f, "Rajadas de E/S: {}, tempo total em E/S={}", self.io_bursts, self.io_time)?;
+        }
+        if self.decks_rejected > 0 {
+            writeln!(f, "Decks rejeitados: {}", self.decks_rejected)?;
+        }
+        if self.jobs_spooled > 0 || self.jobs_printed > 0 {
+            let mean = |total: SimDuration, count: i32| if count == 0 { 0.0 } else { total.ticks() as f64 / count as f64 };
+            writeln!(
+                f,
+                "Spooling: {} jobs lidos ({} cartoes, tempo medio de leitura={:.2}), {} jobs impressos ({} linhas, tempo medio de impressao={:.2}), ocupacao maxima do spool={}",
+                self.jobs_spooled,
+                self.cards_read,
+                mean(self.card_read_time, self.jobs_spooled),
+                self.jobs_printed,
+                self.lines_printed,
+                mean(self.print_time, self.jobs_printed),
+                self.spool_peak
+            )?;
+        }
+        if let Some(policy) = self.disk_policy {
+            writeln!(
+                f,
+                "Disco ({}): {} acessos, deslocamento total={} cilindros, deslocamento medio={:.2}, tempo de resposta medio={:.2}",
+                policy,
+                self.disk_requests,
+                self.disk_seek_distance,
+                self.disk_seek_distance as f64 / self.disk_requests as f64,
+                self.disk_response_time.ticks() as f64 / self.disk_requests as f64
+            )?;
+        }
+        if let (Some(utilisation), Some(fragmentation)) = (self.mean_memory_utilisation(), self.mean_external_fragmentation()) {
+            let peak = self.memory_usage.iter().map(|(_, usage)| usage.external_fragmentation()).fold(0.0, f64::max);
+            let holes = self.memory_usage.iter().map(|(_, usage)| usage.holes.len()).max().unwrap_or(0);
+            writeln!(
+                f,
+                "Memoria: utilizacao media={:.2}%, fragmentacao externa media={:.2}% (maxima {:.2}%), maximo de buracos={}",
+                utilisation * 100.0,
+                fragmentation * 100.0,
+                peak * 100.0,
+                holes
+            )?;
+            let internal = self.memory_usage.iter().map(|(_, usage)| usage.internal_fragmentation).max().unwrap_or(0);
+            if internal > 0 {
+                writeln!(f, "Fragmentacao interna maxima da memoria: {}k", internal)?;
+            }
+        }
+        if self.memory_accesses > 0 {
+            let jobs: Vec<i32> = self.protection_faults.iter().map(|(job_id, _)| *job_id).collect();
+            writeln!(
+                f,
+                "Acessos a memoria: {} ({} falhas de protecao, jobs: {:?})",
+                self.memory_accesses,
+                self.protection_faults.len(),
+                jobs
+            )?;
+        }
+        if self.instructions_executed > 0 {
+            writeln!(f, "Instrucoes executadas: {}", self.instructions_executed)?;
+            let mut output = self.program_output.clone();
+            output.sort_by_key(|&(time, _, _)| time);
+            for (time, job_id, value) in output {
+                writeln!(f, "Saida do job {} no instante {}: {}", job_id, time, value)?;
+            }
+        }
+        match self.mean_turnaround() {
+            Some(mean) => writeln!(f, "Turnaround medio: {:.2}", mean),
+            None => writeln!(f, "Turnaround medio: -"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::system_abstractions::{Job, Memory, PlacementStrategy};
+
+    fn job(id: i32, memory_size: i32) -> Job {
+        Job { id, state: 2, memory_size, cpu_time: SimDuration::from(10) }
+    }
+
+    #[test]
+    fn test_memory_usage_is_weighted_by_time() {
+        let mut memory = Memory::with_placement(100, PlacementStrategy::FirstFit);
+        let mut statistics = Statistics::new();
+        for (time, id) in [(10, 1), (10, 2), (10, 3)] {
+            memory.alloc(job(id, 20), 20).unwrap();
+            statistics.record_memory_usage(SimTime::from(time), memory.usage());
+        }
+        // Freeing the middle segment leaves two holes of 20 and 40
+        memory.dealloc(job(2, 20));
+        let usage = memory.usage();
+        assert_eq!(usage.holes, vec![20, 40]);
+        assert_eq!(usage.largest_hole(), 40);
+        assert!((usage.external_fragmentation() - 20.0 / 60.0).abs() < 1e-9);
+        statistics.record_memory_usage(SimTime::from(30), usage);
+        statistics.final_timestep = SimTime::from(50);
+
+        // 60% used from 10 to 30, 40% from 30 to 50
+        assert!((statistics.mean_memory_utilisation().unwrap() - (0.6 * 20.0 + 0.4 * 20.0) / 50.0).abs() < 1e-9);
+        assert!((statistics.mean_external_fragmentation().unwrap() - (20.0 / 60.0) * 20.0 / 50.0).abs() < 1e-9);
+
+        let mut output = Vec::new();
+        statistics.write_memory_series(&mut output).unwrap();
+        let csv = String::from_utf8(output).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 5);
+        assert_eq!(lines[0], MEMORY_SERIES_HEADER);
+        assert_eq!(lines[4], "30,40,60,0.4000,2,40,0.3333,0,20 40");
+    }
+}
@@ -0,0 +1,171 @@
+// In this file it is implemented the synchronisation primitives
+// jobs use to coordinate with each other: counting semaphores and
+// mutexes. A job that waits on an object that is not available is
+// queued on it; a signal hands the object to the oldest waiting job.
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SyncError {
+    UnknownObject(String),
+    // A job signalled a mutex it does not hold
+    NotOwner(String, i32),
+    // A job waited on a mutex it already holds
+    AlreadyOwner(String, i32),
+}
+
+impl fmt::Display for SyncError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SyncError::UnknownObject(name) => write!(f, "objeto de sincronizacao {} nao existe", name),
+            SyncError::NotOwner(name, job) => write!(f, "job {} liberou o mutex {} sem possui-lo", job, name),
+            SyncError::AlreadyOwner(name, job) => write!(f, "job {} ja possui o mutex {}", job, name),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SyncObject {
+    Semaphore { value: i32, waiting: VecDeque<i32> },
+    Mutex { owner: Option<i32>, waiting: VecDeque<i32> },
+}
+
+#[derive(Debug, Default)]
+pub struct SyncManager {
+    objects: BTreeMap<String, SyncObject>,
+}
+
+impl SyncManager {
+    pub fn new() -> Self {
+        SyncManager { objects: BTreeMap::new() }
+    }
+
+    pub fn create_semaphore(&mut self, name: String, value: i32) {
+        self.objects.insert(name, SyncObject::Semaphore { value, waiting: VecDeque::new() });
+    }
+
+    pub fn create_mutex(&mut self, name: String) {
+        self.objects.insert(name, SyncObject::Mutex { owner: None, waiting: VecDeque::new() });
+    }
+
+    pub fn get(&self, name: &str) -> Option<&SyncObject> {
+        self.objects.get(name)
+    }
+
+    // Returns true if the job got the object, false if it has to wait
+    pub fn wait(&mut self, name: &str, job_id: i32) -> Result<bool, SyncError> {
+        match self.objects.get_mut(name) {
+            Some(SyncObject::Semaphore { value, waiting }) => {
+                if *value > 0 {
+                    *value -= 1;
+                    Ok(true)
+                } else {
+                    waiting.push_back(job_id);
+                    Ok(false)
+                }
+            }
+            Some(SyncObject::Mutex { owner, waiting }) => match owner {
+                None => {
+                    *owner = Some(job_id);
+                    Ok(true)
+                }
+                Some(holder) if *holder == job_id => Err(SyncError::AlreadyOwner(name.to_string(), job_id)),
+                Some(_) => {
+                    waiting.push_back(job_id);
+                    Ok(false)
+                }
+            },
+            None => Err(SyncError::UnknownObject(name.to_string())),
+        }
+    }
+
+    // Returns the job woken up by the signal, if any
+    pub fn signal(&mut self, name: &str, job_id: i32) -> Result<Option<i32>, SyncError> {
+        match self.objects.get_mut(name) {
+            Some(SyncObject::Semaphore { value, waiting }) => {
+                let woken = waiting.pop_front();
+                if woken.is_none() {
+                    *value += 1;
+                }
+                Ok(woken)
+            }
+            Some(SyncObject::Mutex { owner, waiting }) => {
+                if *owner != Some(job_id) {
+                    return Err(SyncError::NotOwner(name.to_string(), job_id));
+                }
+                let woken = waiting.pop_front();
+                *owner = woken;
+                Ok(woken)
+            }
+            None => Err(SyncError::UnknownObject(name.to_string())),
+        }
+    }
+
+    // Takes the job out of the waiting queue of the object, as if it
+    // had never waited on it
+    pub fn cancel_wait(&mut self, name: &str, job_id: i32) {
+        if let Some(SyncObject::Semaphore { waiting, .. } | SyncObject::Mutex { waiting, .. }) = self.objects.get_mut(name) {
+            waiting.retain(|&waiting_job| waiting_job != job_id);
+        }
+    }
+
+    // Drops the job from every waiting queue and releases the mutexes
+    // it holds. Returns the jobs woken up, with the object that woke
+    // each of them.
+    pub fn remove_job(&mut self, job_id: i32) -> Vec<(String, i32)> {
+        let mut woken = Vec::new();
+        for (name, object) in self.objects.iter_mut() {
+            match object {
+                SyncObject::Semaphore { waiting, .. } => waiting.retain(|&waiting_job| waiting_job != job_id),
+                SyncObject::Mutex { owner, waiting } => {
+                    waiting.retain(|&waiting_job| waiting_job != job_id);
+                    if *owner == Some(job_id) {
+                        *owner = waiting.pop_front();
+                        if let Some(next) = *owner {
+                            woken.push((name.clone(), next));
+                        }
+                    }
+                }
+            }
+        }
+        woken
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_semaphore_counts_and_wakes_in_order() {
+        let mut manager = SyncManager::new();
+        manager.create_semaphore(String::from("cheio"), 1);
+
+        assert_eq!(manager.wait("cheio", 1), Ok(true));
+        assert_eq!(manager.wait("cheio", 2), Ok(false));
+        assert_eq!(manager.wait("cheio", 3), Ok(false));
+        assert_eq!(manager.wait("cheio", 5), Ok(false));
+        manager.cancel_wait("cheio", 5);
+
+        assert_eq!(manager.signal("cheio", 1), Ok(Some(2)));
+        assert_eq!(manager.signal("cheio", 2), Ok(Some(3)));
+        assert_eq!(manager.signal("cheio", 3), Ok(None));
+        assert_eq!(manager.wait("cheio", 4), Ok(true));
+    }
+
+    #[test]
+    fn test_mutex_ownership() {
+        let mut manager = SyncManager::new();
+        manager.create_mutex(String::from("buffer"));
+
+        assert_eq!(manager.wait("buffer", 1), Ok(true));
+        assert_eq!(manager.wait("buffer", 1), Err(SyncError::AlreadyOwner(String::from("buffer"), 1)));
+        assert_eq!(manager.wait("buffer", 2), Ok(false));
+        assert_eq!(manager.signal("buffer", 2), Err(SyncError::NotOwner(String::from("buffer"), 2)));
+
+        // Killing the holder passes the mutex on
+        assert_eq!(manager.remove_job(1), vec![(String::from("buffer"), 2)]);
+        assert_eq!(manager.signal("buffer", 2), Ok(None));
+        assert_eq!(manager.wait("nada", 1), Err(SyncError::UnknownObject(String::from("nada"))));
+    }
+}
@@ -1,20 +1,39 @@
 // In this file it is supposed to be implemented system
 // abstractions such as memory, cpu and jobs.
-use crate::event_list::{EventList, Metadata};
+use crate::clock::{Clock, VirtualClock};
+use crate::communication::{ChannelError, ChannelKind, ChannelManager};
+use crate::disk::{Disk, DiskConfig, DiskRequest};
+use crate::event_list::{Event, EventHandle, EventList, Metadata};
+use crate::event_loop::event_loop;
+use crate::file_system::{FileSystem, FileSystemConfig, FileSystemError};
+use crate::job_profile::JobProfile;
+use crate::machine::{Execution, Machine, SegmentCopy};
+use crate::observer::{Observer, QueueChange};
+use crate::protection::{BaseLimit, ProtectionFault};
+use crate::routines::{create_routine, RoutineFactory, Runnable, LOAD_TIME_PER_WORD, TIME_SLICE};
+use crate::resources::{RequestOutcome, ResourceError, ResourceManager, ResourcePolicy};
+use crate::simulation_error::{EventFailure, SimulationError};
+use crate::spooling::{Spool, SpoolingConfig};
+use crate::synchronization::{SyncError, SyncManager};
+use crate::statistics::Statistics;
+use crate::time::{SimDuration, SimTime};
+use crate::trace::Trace;
 use std::sync::{Arc, Mutex};
-use std::collections::{HashMap};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct Job {
     pub id: i32,
     pub state: i32,
     pub memory_size: i32,
-    pub cpu_time: i32,
+    pub cpu_time: SimDuration,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct JobTable {
-    table: HashMap<i32, i32>,
+    // Processing time each job has left
+    table: HashMap<i32, SimDuration>,
 }
 
 impl JobTable {
@@ -22,11 +41,11 @@ impl JobTable {
         JobTable { table: HashMap::new() }
     }
 
-    fn add_job(&mut self, job_id: i32, execution_time: i32) {
+    fn add_job(&mut self, job_id: i32, execution_time: SimDuration) {
         self.table.insert(job_id, execution_time);
     }
 
-    fn pause_job(&mut self, job_id: i32, time_slice: i32) {
+    fn pause_job(&mut self, job_id: i32, time_slice: SimDuration) {
         if let Some(remaining_time) = self.table.get_mut(&job_id) {
             *remaining_time -= time_slice;
         }
@@ -36,12 +55,8 @@ impl JobTable {
         self.table.remove(&job_id);
     }
 
-    fn get_time_remaining(&mut self, job_id: i32) -> i32 {
-        if let Some(remaining_time) = self.table.get_mut(&job_id) {
-            *remaining_time
-        } else {
-            -1
-        }
+    fn get_time_remaining(&self, job_id: i32) -> Option<SimDuration> {
+        self.table.get(&job_id).copied()
     }
 
     fn len(&self) -> i32 {
@@ -58,22 +73,180 @@ pub struct Segment {
     id: i32,
     start_address: i32,
     size: i32,
+    // Memory the segment takes, larger than its size when the
+    // allocator rounds it up
+    block_size: i32,
     owner: Option<Job>,
 }
 
+// Which free gap of the memory a new segment goes to
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum PlacementStrategy {
+    // The gap with the lowest address
+    #[default]
+    FirstFit,
+    // The smallest gap the segment fits in
+    BestFit,
+    // The largest gap
+    WorstFit,
+}
+
+impl fmt::Display for PlacementStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlacementStrategy::FirstFit => write!(f, "first-fit"),
+            PlacementStrategy::BestFit => write!(f, "best-fit"),
+            PlacementStrategy::WorstFit => write!(f, "worst-fit"),
+        }
+    }
+}
+
+// How the processor is shared among the jobs in memory
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum SchedulerPolicy {
+    // Each job runs for at most one time slice at a time
+    #[default]
+    RoundRobin,
+    // A job keeps the processor until it ends or blocks
+    Fcfs,
+}
+
+impl fmt::Display for SchedulerPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SchedulerPolicy::RoundRobin => write!(f, "round-robin"),
+            SchedulerPolicy::Fcfs => write!(f, "fcfs"),
+        }
+    }
+}
+
+// How the memory is divided among the jobs
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum MemoryAllocator {
+    // Segments of the exact size requested, placed in the free gaps
+    // by the placement strategy
+    #[default]
+    VariablePartition,
+    // Blocks of power-of-two sizes, split in halves to fit a segment
+    // and merged back with their buddy when freed
+    Buddy,
+    // Partitions of sizes fixed beforehand, one job in each, as in
+    // OS/360 MFT
+    FixedPartitions(PartitionQueues),
+}
+
+// How the jobs waiting for memory are routed to fixed partitions
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum PartitionQueues {
+    // One queue for all partitions: a job takes the smallest free
+    // partition it fits in
+    #[default]
+    Single,
+    // One queue per partition: a job waits for the smallest partition
+    // it fits in, even while a larger one is free
+    PerPartition,
+}
+
+impl fmt::Display for MemoryAllocator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MemoryAllocator::VariablePartition => write!(f, "variable-partition"),
+            MemoryAllocator::Buddy => write!(f, "buddy"),
+            MemoryAllocator::FixedPartitions(PartitionQueues::Single) => write!(f, "fixed-partition-single-queue"),
+            MemoryAllocator::FixedPartitions(PartitionQueues::PerPartition) => write!(f, "fixed-partition-per-queue"),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Memory {
     total_memory: i32,
     next_segment_id: i32,
     segments: Vec<Segment>,
+    placement: PlacementStrategy,
+    allocator: MemoryAllocator,
+    // Start addresses of the free blocks of each size, for the buddy
+    // allocator
+    free_blocks: BTreeMap<i32, BTreeSet<i32>>,
+    // (start address, size) of every fixed partition, in address order
+    partitions: Vec<(i32, i32)>,
+    // Word at each physical address
+    contents: Vec<i32>,
 }
 
 impl Memory {
     pub fn new(number: i32) -> Self {
-        Memory {
+        Self::with_placement(number, PlacementStrategy::FirstFit)
+    }
+
+    pub fn with_placement(number: i32, placement: PlacementStrategy) -> Self {
+        Self::with_allocator(number, MemoryAllocator::VariablePartition, placement)
+    }
+
+    // Fixed partitions take the whole memory as a single partition;
+    // use with_partitions to divide it
+    pub fn with_allocator(number: i32, allocator: MemoryAllocator, placement: PlacementStrategy) -> Self {
+        if let MemoryAllocator::FixedPartitions(queues) = allocator {
+            return Self::with_partitions(&[number], queues);
+        }
+        let mut memory = Memory {
             total_memory: number,
             next_segment_id: 1,
             segments: Vec::new(),
+            placement,
+            allocator,
+            free_blocks: BTreeMap::new(),
+            partitions: Vec::new(),
+            contents: vec![0; number.max(0) as usize],
+        };
+        if allocator == MemoryAllocator::Buddy {
+            // A memory that is not a power of two starts as the largest
+            // blocks that fit in it, each aligned to its size
+            let mut start_address = 0;
+            while start_address < number {
+                let block_size = 1 << (31 - (number - start_address).leading_zeros());
+                memory.free_blocks.entry(block_size).or_default().insert(start_address);
+                start_address += block_size;
+            }
+        }
+        memory
+    }
+
+    // A memory divided in fixed partitions of the given sizes, laid
+    // out from address 0 in order
+    pub fn with_partitions(sizes: &[i32], queues: PartitionQueues) -> Self {
+        let mut partitions = Vec::new();
+        let mut start_address = 0;
+        for &size in sizes {
+            partitions.push((start_address, size));
+            start_address += size;
+        }
+        Memory {
+            total_memory: start_address,
+            next_segment_id: 1,
+            segments: Vec::new(),
+            placement: PlacementStrategy::FirstFit,
+            allocator: MemoryAllocator::FixedPartitions(queues),
+            free_blocks: BTreeMap::new(),
+            partitions,
+            contents: vec![0; start_address.max(0) as usize],
+        }
+    }
+
+    pub fn read(&self, address: i32) -> Option<i32> {
+        self.contents.get(usize::try_from(address).ok()?).copied()
+    }
+
+    pub fn write(&mut self, address: i32, value: i32) {
+        if let Some(word) = usize::try_from(address).ok().and_then(|address| self.contents.get_mut(address)) {
+            *word = value;
+        }
+    }
+
+    // Copies the words from the address on
+    pub fn load(&mut self, address: i32, words: &[i32]) {
+        for (offset, &word) in words.iter().enumerate() {
+            self.write(address + offset as i32, word);
         }
     }
 
@@ -84,6 +257,7 @@ impl Memory {
                 id: self.next_segment_id,
                 start_address: segment.start_address,
                 size,
+                block_size: segment.block_size,
                 owner: Some(job.clone()),
             });
             println!(
@@ -93,6 +267,13 @@ impl Memory {
                 segment.size,
                 job.id
             );
+            if segment.block_size > segment.size {
+                println!(
+                    "Bloco de {}k, fragmentacao interna de {}k",
+                    segment.block_size,
+                    segment.block_size - segment.size
+                );
+            }
             self.next_segment_id += 1;
             Ok(segment)
         } else {
@@ -100,13 +281,15 @@ impl Memory {
         }
     }
 
-    pub fn dealloc(&mut self, job: Job) {
+    // Frees every segment owned by the job, returning the amount of
+    // memory released
+    pub fn dealloc(&mut self, job: Job) -> i32 {
         let indices: Vec<usize> = self
             .segments
             .iter()
             .enumerate()
             .filter_map(|(index, segment)| {
-                if segment.owner.as_ref().map_or(false, |owner| owner.id == job.id) {
+                if segment.owner.as_ref().is_some_and(|owner| owner.id == job.id) {
                     Some(index)
                 } else {
                     None
@@ -114,6 +297,7 @@ impl Memory {
             })
             .collect();
 
+        let mut freed = 0;
         for &index in indices.iter().rev() {
             let segment = self.segments.remove(index);
             // Deallocate the memory used by the segment
@@ -123,54 +307,227 @@ impl Memory {
                 segment.start_address,
                 segment.size,
                 job.id
-            ); 
+            );
+            if self.allocator == MemoryAllocator::Buddy {
+                self.free_buddy_block(segment.start_address, segment.block_size);
+            }
+            freed += segment.block_size;
         }
+        freed
+    }
+
+    // Whether some job holds a segment
+    pub fn has_jobs(&self) -> bool {
+        self.segments.iter().any(|segment| segment.owner.is_some())
     }
 
     pub fn available_memory(&self) -> i32 {
-        self.total_memory - self.segments.iter().map(|s| s.size).sum::<i32>()
+        self.total_memory - self.segments.iter().map(|s| s.block_size).sum::<i32>()
     }
 
-    fn allocate_segment(&mut self, size: i32) -> Option<Segment> {
+    pub fn allocator(&self) -> MemoryAllocator {
+        self.allocator
+    }
+
+    pub fn usage(&self) -> MemoryUsage {
+        let holes = match self.allocator {
+            MemoryAllocator::VariablePartition => self.gaps(),
+            MemoryAllocator::FixedPartitions(_) => {
+                self.partitions.iter().copied().filter(|&(start, _)| self.partition_is_free(start)).collect()
+            }
+            MemoryAllocator::Buddy => {
+                let mut blocks: Vec<(i32, i32)> = self
+                    .free_blocks
+                    .iter()
+                    .flat_map(|(&block_size, starts)| starts.iter().map(move |&start| (start, block_size)))
+                    .collect();
+                blocks.sort();
+                blocks
+            }
+        };
+        MemoryUsage {
+            total: self.total_memory,
+            used: self.total_memory - self.available_memory(),
+            holes: holes.into_iter().map(|(_, size)| size).collect(),
+            internal_fragmentation: self.segments.iter().map(|segment| segment.block_size - segment.size).sum(),
+        }
+    }
+
+    // (start address, size) of every free gap, in address order
+    fn gaps(&self) -> Vec<(i32, i32)> {
+        let mut occupied: Vec<(i32, i32)> =
+            self.segments.iter().map(|segment| (segment.start_address, segment.end_address())).collect();
+        occupied.sort();
+
+        let mut gaps = Vec::new();
         let mut start_address = 0;
+        for (start, end) in occupied {
+            if start > start_address {
+                gaps.push((start_address, start - start_address));
+            }
+            start_address = start_address.max(end);
+        }
+        if self.total_memory > start_address {
+            gaps.push((start_address, self.total_memory - start_address));
+        }
+        gaps
+    }
 
-        for segment in &self.segments {
-            let gap_size = segment.start_address - start_address;
-            if gap_size >= size {
-                // Found a suitable gap
-                return Some(Segment {
-                    id: 0,
-                    start_address,
-                    size,
-                    owner: None,
-                });
+    // Picks the free gap for a segment of the given size according to
+    // the placement strategy
+    fn allocate_segment(&mut self, size: i32) -> Option<Segment> {
+        match self.allocator {
+            MemoryAllocator::Buddy => return self.allocate_buddy_block(size),
+            MemoryAllocator::FixedPartitions(queues) => {
+                let (start_address, block_size) = self.find_partition(size, queues)?;
+                return Some(Segment { id: 0, start_address, size, block_size, owner: None });
             }
-            start_address = segment.end_address();
+            MemoryAllocator::VariablePartition => {}
         }
+        let mut fitting = self.gaps().into_iter().filter(|&(_, gap_size)| gap_size >= size);
+        let chosen = match self.placement {
+            PlacementStrategy::FirstFit => fitting.next(),
+            PlacementStrategy::BestFit => fitting.min_by_key(|&(start, gap_size)| (gap_size, start)),
+            PlacementStrategy::WorstFit => fitting.min_by_key(|&(start, gap_size)| (-gap_size, start)),
+        };
+        chosen.map(|(start_address, _)| Segment { id: 0, start_address, size, block_size: size, owner: None })
+    }
 
-        // Check for available memory after the last segment
-        let remaining_memory = self.total_memory - start_address;
-        if remaining_memory >= size {
-            return Some(Segment {
-                id: 0, // You can set the correct ID when inserting into the segments vector
-                start_address,
-                size,
-                owner: None,
-            });
+    // Whether a segment of the given size could be allocated now
+    pub fn can_alloc(&self, size: i32) -> bool {
+        match self.allocator {
+            MemoryAllocator::VariablePartition => self.gaps().iter().any(|&(_, gap_size)| gap_size >= size),
+            MemoryAllocator::Buddy => {
+                self.buddy_block_size(size).is_some_and(|needed| self.free_blocks.range(needed..).next().is_some())
+            }
+            MemoryAllocator::FixedPartitions(queues) => self.find_partition(size, queues).is_some(),
         }
+    }
 
-        // If no suitable gap is found, return None
-        None
+    // The partition a segment of the given size goes to, if it is free
+    fn find_partition(&self, size: i32, queues: PartitionQueues) -> Option<(i32, i32)> {
+        let mut fitting: Vec<(i32, i32)> =
+            self.partitions.iter().copied().filter(|&(_, partition_size)| partition_size >= size).collect();
+        fitting.sort_by_key(|&(start, partition_size)| (partition_size, start));
+        match queues {
+            PartitionQueues::Single => fitting.into_iter().find(|&(start, _)| self.partition_is_free(start)),
+            // The job belongs to the smallest partition of its size
+            PartitionQueues::PerPartition => {
+                let smallest = fitting.first()?.1;
+                fitting
+                    .into_iter()
+                    .take_while(|&(_, partition_size)| partition_size == smallest)
+                    .find(|&(start, _)| self.partition_is_free(start))
+            }
+        }
+    }
+
+    fn partition_is_free(&self, start_address: i32) -> bool {
+        !self.segments.iter().any(|segment| segment.start_address == start_address)
+    }
+
+    // Takes the smallest free block the segment fits in, splitting it
+    // in halves until the segment no longer fits in half of it
+    fn allocate_buddy_block(&mut self, size: i32) -> Option<Segment> {
+        let needed = self.buddy_block_size(size)?;
+        let (&found_size, starts) = self.free_blocks.range(needed..).next()?;
+        let start_address = *starts.first()?;
+        self.take_free_block(found_size, start_address);
+
+        let mut block_size = found_size;
+        while block_size > needed {
+            block_size /= 2;
+            self.free_blocks.entry(block_size).or_default().insert(start_address + block_size);
+        }
+        Some(Segment { id: 0, start_address, size, block_size, owner: None })
+    }
+
+    // Size of the block a segment takes: its size rounded up to a power
+    // of two. None for a segment larger than the whole memory.
+    fn buddy_block_size(&self, size: i32) -> Option<i32> {
+        if size > self.total_memory {
+            return None;
+        }
+        let needed = u32::try_from(size.max(1)).ok()?.checked_next_power_of_two()?;
+        i32::try_from(needed).ok()
+    }
+
+    // Returns the block to the free blocks, merging it with its buddy
+    // for as long as the buddy is free as well
+    fn free_buddy_block(&mut self, mut start_address: i32, mut block_size: i32) {
+        loop {
+            let buddy = start_address ^ block_size;
+            if !self.free_blocks.get(&block_size).is_some_and(|starts| starts.contains(&buddy)) {
+                break;
+            }
+            self.take_free_block(block_size, buddy);
+            start_address = start_address.min(buddy);
+            block_size *= 2;
+        }
+        self.free_blocks.entry(block_size).or_default().insert(start_address);
+    }
+
+    fn take_free_block(&mut self, block_size: i32, start_address: i32) {
+        if let Some(starts) = self.free_blocks.get_mut(&block_size) {
+            starts.remove(&start_address);
+            if starts.is_empty() {
+                self.free_blocks.remove(&block_size);
+            }
+        }
+    }
+}
+
+// Occupation of the memory at one instant
+#[derive(Debug, Clone, PartialEq)]
+pub struct MemoryUsage {
+    pub total: i32,
+    pub used: i32,
+    // Size of every free gap, in address order
+    pub holes: Vec<i32>,
+    // Memory taken by the segments beyond what they asked for
+    pub internal_fragmentation: i32,
+}
+
+impl MemoryUsage {
+    pub fn free(&self) -> i32 {
+        self.total - self.used
+    }
+
+    pub fn utilisation(&self) -> f64 {
+        if self.total == 0 { 0.0 } else { self.used as f64 / self.total as f64 }
+    }
+
+    pub fn largest_hole(&self) -> i32 {
+        self.holes.iter().copied().max().unwrap_or(0)
+    }
+
+    // Share of the free memory outside the largest gap: 0 when it is
+    // all in one gap, close to 1 when it is spread in small ones
+    pub fn external_fragmentation(&self) -> f64 {
+        let free = self.free();
+        if free == 0 { 0.0 } else { (free - self.largest_hole()) as f64 / free as f64 }
     }
 }
 
 impl Segment {
+    pub fn start_address(&self) -> i32 {
+        self.start_address
+    }
+
+    pub fn size(&self) -> i32 {
+        self.size
+    }
+
+    pub fn block_size(&self) -> i32 {
+        self.block_size
+    }
+
     fn end_address(&self) -> i32 {
-        self.start_address + self.size
+        self.start_address + self.block_size
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct SystemEntryQueue {
     jobs: Vec<Job>,
 }
@@ -191,9 +548,14 @@ impl SystemEntryQueue {
     pub fn is_empty(&self) -> bool {
         self.jobs.is_empty()
     }
+
+    pub fn remove_job_by_id(&mut self, job_id: i32) -> Option<Job> {
+        let index = self.jobs.iter().position(|job| job.id == job_id)?;
+        Some(self.jobs.remove(index))
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct MemoryAllocQueue {
     jobs: Vec<Job>,
 }
@@ -210,13 +572,25 @@ impl MemoryAllocQueue {
     pub fn remove_job(&mut self) -> Option<Job> {
         self.jobs.pop()
     }
+
+    // Removes the job waiting the longest among those that satisfy
+    // the condition
+    pub fn remove_first_job_where(&mut self, condition: impl Fn(&Job) -> bool) -> Option<Job> {
+        let index = self.jobs.iter().rposition(condition)?;
+        Some(self.jobs.remove(index))
+    }
     
     pub fn is_empty(&self) -> bool {
         self.jobs.is_empty()
     }
+
+    pub fn remove_job_by_id(&mut self, job_id: i32) -> Option<Job> {
+        let index = self.jobs.iter().position(|job| job.id == job_id)?;
+        Some(self.jobs.remove(index))
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct CPUAllocQueue {
     jobs: Vec<Job>,
 }
@@ -233,9 +607,22 @@ impl CPUAllocQueue {
     pub fn remove_job(&mut self) -> Option<Job> {
         self.jobs.pop()
     }
+
+    pub fn is_empty(&self) -> bool {
+        self.jobs.is_empty()
+    }
+
+    pub fn contains(&self, job_id: i32) -> bool {
+        self.jobs.iter().any(|job| job.id == job_id)
+    }
+
+    pub fn remove_job_by_id(&mut self, job_id: i32) -> Option<Job> {
+        let index = self.jobs.iter().position(|job| job.id == job_id)?;
+        Some(self.jobs.remove(index))
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct ExecQueue {
     jobs: Vec<Job>,
 }
@@ -256,6 +643,113 @@ impl ExecQueue {
     pub fn is_empty(&self) -> bool {
         self.jobs.is_empty()
     }
+
+    pub fn contains(&self, job_id: i32) -> bool {
+        self.jobs.iter().any(|job| job.id == job_id)
+    }
+
+    pub fn remove_job_by_id(&mut self, job_id: i32) -> Option<Job> {
+        let index = self.jobs.iter().position(|job| job.id == job_id)?;
+        Some(self.jobs.remove(index))
+    }
+}
+
+// Jobs waiting for something other than memory or the processor,
+// such as resources, along with the reason they are waiting
+#[derive(Debug, Default)]
+pub struct BlockedQueue {
+    jobs: Vec<(Job, String)>,
+}
+
+impl BlockedQueue {
+    pub fn new() -> Self {
+        BlockedQueue { jobs: Vec::new() }
+    }
+
+    pub fn add_job(&mut self, job: Job, reason: String) {
+        self.jobs.push((job, reason));
+    }
+
+    pub fn remove_job_by_id(&mut self, job_id: i32) -> Option<Job> {
+        let index = self.jobs.iter().position(|(job, _)| job.id == job_id)?;
+        Some(self.jobs.remove(index).0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.jobs.is_empty()
+    }
+
+    pub fn contains(&self, job_id: i32) -> bool {
+        self.jobs.iter().any(|(job, _)| job.id == job_id)
+    }
+
+    // Blocked jobs with the reason they are waiting, oldest first
+    pub fn jobs(&self) -> &[(Job, String)] {
+        &self.jobs
+    }
+}
+
+// Parameters of the simulated system that are not events
+#[derive(Debug, Clone)]
+pub struct SystemConfig {
+    pub memory_size: i32,
+    pub memory_allocator: MemoryAllocator,
+    // Sizes of the fixed partitions, in address order; under fixed
+    // partitions they replace memory_size
+    pub partitions: Vec<i32>,
+    pub placement: PlacementStrategy,
+    pub scheduler: SchedulerPolicy,
+    // Processing time a job gets each time it is handed the
+    // processor, under round robin
+    pub time_slice: SimDuration,
+    // Most jobs sharing the memory and the processor at a time
+    pub multiprogramming_level: i32,
+    // Time the loader takes for each word of a program
+    pub load_time_per_word: SimDuration,
+    // Name and number of units of each resource type
+    pub resources: Vec<(String, i32)>,
+    pub resource_policy: ResourcePolicy,
+    // Name and initial value of each counting semaphore
+    pub semaphores: Vec<(String, i32)>,
+    pub mutexes: Vec<String>,
+    // Name, kind, capacity and latency of each channel
+    pub channels: Vec<(String, ChannelKind, i32, SimDuration)>,
+    pub file_system: FileSystemConfig,
+    pub disk: DiskConfig,
+    // Card reader and printer timings; None runs without spooling
+    pub spooling: Option<SpoolingConfig>,
+}
+
+impl Default for SystemConfig {
+    fn default() -> Self {
+        SystemConfig {
+            memory_size: 128,
+            memory_allocator: MemoryAllocator::VariablePartition,
+            partitions: Vec::new(),
+            placement: PlacementStrategy::FirstFit,
+            scheduler: SchedulerPolicy::RoundRobin,
+            time_slice: TIME_SLICE,
+            multiprogramming_level: 2,
+            load_time_per_word: LOAD_TIME_PER_WORD,
+            resources: Vec::new(),
+            resource_policy: ResourcePolicy::Avoidance,
+            semaphores: Vec::new(),
+            mutexes: Vec::new(),
+            channels: Vec::new(),
+            file_system: FileSystemConfig::default(),
+            disk: DiskConfig::default(),
+            spooling: None,
+        }
+    }
+}
+
+// The processor time a job running a program got: the instant it
+// started and, if the program stops before the slice ends, the event
+// scheduled for that stop
+#[derive(Debug, Clone, Copy)]
+pub struct ProgramSlice {
+    pub start: SimTime,
+    pub stop_event: Option<EventHandle>,
 }
 
 pub struct SharedState {
@@ -265,11 +759,39 @@ pub struct SharedState {
     cpu_alloc_queue: Arc<Mutex<CPUAllocQueue>>,
     exec_queue: Arc<Mutex<ExecQueue>>,
     memory: Arc<Mutex<Memory>>,
-    pub current_timestep: i32,
+    pub current_timestep: SimTime,
     job_table: Arc<Mutex<JobTable>>,
+    trace: Arc<Mutex<Trace>>,
+    statistics: Arc<Mutex<Statistics>>,
+    blocked_queue: Arc<Mutex<BlockedQueue>>,
+    resource_manager: Arc<Mutex<ResourceManager>>,
+    job_priorities: Arc<Mutex<HashMap<i32, i32>>>,
+    sync_manager: Arc<Mutex<SyncManager>>,
+    channel_manager: Arc<Mutex<ChannelManager>>,
+    file_system: Arc<Mutex<FileSystem>>,
+    disk: Arc<Mutex<Disk>>,
+    job_profiles: Arc<Mutex<HashMap<i32, JobProfile>>>,
+    // Instant each I/O device becomes free
+    io_devices: Arc<Mutex<HashMap<String, SimTime>>>,
+    spool: Arc<Mutex<Option<Spool>>>,
+    // Base and limit registers of each job in memory, set when its
+    // segment is allocated
+    registers: Arc<Mutex<HashMap<i32, BaseLimit>>>,
+    // Machine of each job that runs a program
+    machines: Arc<Mutex<HashMap<i32, Machine>>>,
+    // Time slice of each job running a program; its instructions run
+    // when the processor is taken back
+    program_slices: Arc<Mutex<HashMap<i32, ProgramSlice>>>,
+    time_slice: SimDuration,
+    multiprogramming_level: i32,
+    load_time_per_word: SimDuration,
+    observers: Arc<Mutex<Vec<Box<dyn Observer + Send>>>>,
+    // User-defined routines, by routine name
+    routines: Arc<Mutex<HashMap<String, RoutineFactory>>>,
 }
 
 impl SharedState {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         event_list: EventList,
         system_entry_queue: SystemEntryQueue,
@@ -277,7 +799,7 @@ impl SharedState {
         cpu_alloc_queue: CPUAllocQueue,
         exec_queue: ExecQueue,
         memory: Memory,
-        current_timestep: i32,
+        current_timestep: SimTime,
         job_table: JobTable,
     ) -> Self {
         SharedState {
@@ -289,9 +811,79 @@ impl SharedState {
             memory: Arc::new(Mutex::new(memory)),
             current_timestep,
             job_table: Arc::new(Mutex::new(job_table)),
+            trace: Arc::new(Mutex::new(Trace::new())),
+            statistics: Arc::new(Mutex::new(Statistics::new())),
+            blocked_queue: Arc::new(Mutex::new(BlockedQueue::new())),
+            resource_manager: Arc::new(Mutex::new(ResourceManager::default())),
+            job_priorities: Arc::new(Mutex::new(HashMap::new())),
+            sync_manager: Arc::new(Mutex::new(SyncManager::new())),
+            channel_manager: Arc::new(Mutex::new(ChannelManager::new())),
+            file_system: Arc::new(Mutex::new(FileSystem::default())),
+            disk: Arc::new(Mutex::new(Disk::default())),
+            job_profiles: Arc::new(Mutex::new(HashMap::new())),
+            io_devices: Arc::new(Mutex::new(HashMap::new())),
+            spool: Arc::new(Mutex::new(None)),
+            registers: Arc::new(Mutex::new(HashMap::new())),
+            machines: Arc::new(Mutex::new(HashMap::new())),
+            program_slices: Arc::new(Mutex::new(HashMap::new())),
+            time_slice: TIME_SLICE,
+            multiprogramming_level: 2,
+            load_time_per_word: LOAD_TIME_PER_WORD,
+            observers: Arc::new(Mutex::new(Vec::new())),
+            routines: Arc::new(Mutex::new(HashMap::new())),
         }
     }
 
+    // Builds empty queues and job table around the event list. With
+    // deadlock detection, the first check is scheduled as well.
+    pub fn from_config(mut event_list: EventList, config: &SystemConfig) -> Self {
+        if let ResourcePolicy::Detection { period, .. } = config.resource_policy {
+            event_list.push(SimTime::ZERO + period.duration(), "Deteccao de deadlock".to_string(), Metadata::DetectDeadlock);
+        }
+        let mut shared_state = SharedState::new(
+            event_list,
+            SystemEntryQueue::new(),
+            MemoryAllocQueue::new(),
+            CPUAllocQueue::new(),
+            ExecQueue::new(),
+            match config.memory_allocator {
+                MemoryAllocator::FixedPartitions(queues) if !config.partitions.is_empty() => {
+                    Memory::with_partitions(&config.partitions, queues)
+                }
+                allocator => Memory::with_allocator(config.memory_size, allocator, config.placement),
+            },
+            SimTime::ZERO,
+            JobTable::new(),
+        );
+        *shared_state.resource_manager.lock().unwrap() =
+            ResourceManager::with_policy(config.resources.clone(), config.resource_policy);
+        {
+            let mut sync_manager = shared_state.sync_manager.lock().unwrap();
+            for (name, value) in &config.semaphores {
+                sync_manager.create_semaphore(name.clone(), *value);
+            }
+            for name in &config.mutexes {
+                sync_manager.create_mutex(name.clone());
+            }
+        }
+        {
+            let mut channel_manager = shared_state.channel_manager.lock().unwrap();
+            for (name, kind, capacity, latency) in &config.channels {
+                channel_manager.create_channel(name.clone(), *kind, *capacity, *latency);
+            }
+        }
+        *shared_state.file_system.lock().unwrap() = FileSystem::new(config.file_system);
+        *shared_state.disk.lock().unwrap() = Disk::new(config.disk);
+        shared_state.time_slice = match config.scheduler {
+            SchedulerPolicy::RoundRobin => config.time_slice,
+            SchedulerPolicy::Fcfs => SimDuration::MAX,
+        };
+        shared_state.multiprogramming_level = config.multiprogramming_level;
+        shared_state.load_time_per_word = config.load_time_per_word;
+        *shared_state.spool.lock().unwrap() = config.spooling.clone().map(Spool::new);
+        shared_state
+    }
+
     pub fn get_event_list(&self) -> Arc<Mutex<EventList>> {
         self.event_list.clone()
     }
@@ -319,69 +911,334 @@ impl SharedState {
     pub fn get_job_table(&self) -> Arc<Mutex<JobTable>> {
         self.job_table.clone()
     }
+
+    pub fn get_trace(&self) -> Arc<Mutex<Trace>> {
+        self.trace.clone()
+    }
+
+    pub fn get_statistics(&self) -> Arc<Mutex<Statistics>> {
+        self.statistics.clone()
+    }
+
+    pub fn get_blocked_queue(&self) -> Arc<Mutex<BlockedQueue>> {
+        self.blocked_queue.clone()
+    }
+
+    pub fn get_resource_manager(&self) -> Arc<Mutex<ResourceManager>> {
+        self.resource_manager.clone()
+    }
+
+    pub fn get_job_priorities(&self) -> Arc<Mutex<HashMap<i32, i32>>> {
+        self.job_priorities.clone()
+    }
+
+    pub fn get_sync_manager(&self) -> Arc<Mutex<SyncManager>> {
+        self.sync_manager.clone()
+    }
+
+    pub fn get_channel_manager(&self) -> Arc<Mutex<ChannelManager>> {
+        self.channel_manager.clone()
+    }
+
+    pub fn get_file_system(&self) -> Arc<Mutex<FileSystem>> {
+        self.file_system.clone()
+    }
+
+    pub fn get_disk(&self) -> Arc<Mutex<Disk>> {
+        self.disk.clone()
+    }
+
+    pub fn get_job_profiles(&self) -> Arc<Mutex<HashMap<i32, JobProfile>>> {
+        self.job_profiles.clone()
+    }
+
+    pub fn get_io_devices(&self) -> Arc<Mutex<HashMap<String, SimTime>>> {
+        self.io_devices.clone()
+    }
+
+    pub fn get_spool(&self) -> Arc<Mutex<Option<Spool>>> {
+        self.spool.clone()
+    }
+
+    pub fn get_registers(&self) -> Arc<Mutex<HashMap<i32, BaseLimit>>> {
+        self.registers.clone()
+    }
+
+    pub fn get_machines(&self) -> Arc<Mutex<HashMap<i32, Machine>>> {
+        self.machines.clone()
+    }
+
+    pub fn get_program_slices(&self) -> Arc<Mutex<HashMap<i32, ProgramSlice>>> {
+        self.program_slices.clone()
+    }
+
+    pub fn get_observers(&self) -> Arc<Mutex<Vec<Box<dyn Observer + Send>>>> {
+        self.observers.clone()
+    }
+
+    pub fn get_routines(&self) -> Arc<Mutex<HashMap<String, RoutineFactory>>> {
+        self.routines.clone()
+    }
 }
 
 pub struct ControlModule {
     pub shared_state: SharedState,
+    clock: Box<dyn Clock>,
 }
 
 impl ControlModule {
+    // Creates a control module driven by a virtual clock
     pub fn new(shared_state: SharedState) -> Self {
-        ControlModule { shared_state }
+        ControlModule::with_clock(shared_state, Box::new(VirtualClock::new()))
+    }
+
+    pub fn with_clock(shared_state: SharedState, clock: Box<dyn Clock>) -> Self {
+        ControlModule { shared_state, clock }
+    }
+
+    // Handles the events of the current timestep and lets the clock
+    // move the simulation forward until it stops. Returns the last
+    // timestep handled.
+    pub fn run(&mut self, event_to_routine: &HashMap<&str, &str>) -> Result<SimTime, Box<EventFailure>> {
+        loop {
+            let timestep = self.get_current_timestep();
+            println!("Instante de simulacao: {}", timestep);
+
+            let next_event = event_loop(event_to_routine, timestep, self)?;
+            match self.clock.next_timestep(timestep, next_event) {
+                Some(next_timestep) => self.update_current_timestep(next_timestep),
+                None => return Ok(timestep),
+            }
+        }
+    }
+
+    // Handles the events mapped to the routine name with the given
+    // factory, replacing the built-in routine of that name if there
+    // is one. New event names are mapped in the event_to_routine map
+    // passed to run; events of a new kind carry their fields in
+    // Metadata::Custom.
+    pub fn register_routine(&self, routine: &str, factory: RoutineFactory) {
+        self.shared_state.get_routines().lock().unwrap().insert(routine.to_string(), factory);
+    }
+
+    pub fn create_routine(&self, routine: &str, metadata: &Metadata) -> Box<dyn Runnable> {
+        let routines = self.shared_state.get_routines();
+        let routines = routines.lock().unwrap();
+        match routines.get(routine) {
+            Some(factory) => factory(metadata),
+            None => create_routine(routine, metadata),
+        }
     }
 
-    pub fn add_event(&self, time: i32, name: String, metadata: Metadata) {
+    // Observers are called in the order they were added
+    pub fn add_observer(&self, observer: Box<dyn Observer + Send>) {
+        self.shared_state.get_observers().lock().unwrap().push(observer);
+    }
+
+    pub fn notify<F: FnMut(&mut dyn Observer)>(&self, mut notification: F) {
+        let observers = self.shared_state.get_observers();
+        let mut observers = observers.lock().unwrap();
+        for observer in observers.iter_mut() {
+            notification(observer.as_mut());
+        }
+    }
+
+    fn notify_queue(&self, queue: &'static str, change: QueueChange, job: Option<&Job>) {
+        if let Some(job) = job {
+            let now = self.get_current_timestep();
+            self.notify(|observer| observer.queue_changed(now, queue, change, job.id));
+        }
+    }
+
+    // Moves the job to a new state, telling the observers
+    pub fn set_job_state(&self, job: &mut Job, state: i32) {
+        let (now, old_state) = (self.get_current_timestep(), job.state);
+        job.state = state;
+        self.notify(|observer| observer.job_state_changed(now, job.id, old_state, state));
+    }
+
+    // Schedules the event for the current instant, after the events
+    // already due now, so follow-up events run in the order they are
+    // scheduled
+    pub fn schedule_now(&self, name: String, metadata: Metadata) -> EventHandle {
+        self.schedule_at(self.get_current_timestep(), name, metadata)
+    }
+
+    pub fn schedule_after(&self, delay: SimDuration, name: String, metadata: Metadata) -> EventHandle {
+        self.schedule_at(self.get_current_timestep() + delay, name, metadata)
+    }
+
+    // Schedules the event for an absolute instant, after the events
+    // already scheduled for it. An instant already past is taken as
+    // the current one, since the simulation never goes back.
+    pub fn schedule_at(&self, time: SimTime, name: String, metadata: Metadata) -> EventHandle {
+        let time = time.max(self.get_current_timestep());
+        let event_list = self.shared_state.get_event_list();
+        let mut list = event_list.lock().unwrap();
+        list.push(time, name, metadata)
+    }
+
+    pub fn has_pending_events(&self) -> bool {
+        let event_list = self.shared_state.get_event_list();
+        let list = event_list.lock().unwrap();
+        !list.is_empty()
+    }
+
+    pub fn cancel_event(&self, handle: EventHandle) -> Option<Event> {
         let event_list = self.shared_state.get_event_list();
         let mut list = event_list.lock().unwrap();
-        list.push(time, name, metadata);
+        list.remove(handle)
+    }
+
+    pub fn reschedule_event(&self, handle: EventHandle, time: impl Into<SimTime>) -> bool {
+        let event_list = self.shared_state.get_event_list();
+        let mut list = event_list.lock().unwrap();
+        list.reschedule(handle, time)
+    }
+
+    // Handle, time and name of every pending event that refers to the
+    // job
+    pub fn pending_events_for_job(&self, job_id: i32) -> Vec<(EventHandle, SimTime, String)> {
+        let event_list = self.shared_state.get_event_list();
+        let list = event_list.lock().unwrap();
+        list.events_for_job(job_id)
+            .into_iter()
+            .map(|event| (event.handle, event.time, event.name.clone()))
+            .collect()
     }
 
     pub fn add_SEQ(&self, job: Job) {
         let system_entry_queue = self.shared_state.get_system_entry_queue();
         let mut queue = system_entry_queue.lock().unwrap();
+        self.notify_queue("SystemEntryQueue", QueueChange::Added, Some(&job));
         queue.add_job(job);
     }
 
     pub fn remove_SEQ(&self) -> Option<Job> {
         let system_entry_queue = self.shared_state.get_system_entry_queue();
         let mut queue = system_entry_queue.lock().unwrap();
-        queue.remove_job()
+        let job = queue.remove_job();
+        self.notify_queue("SystemEntryQueue", QueueChange::Removed, job.as_ref());
+        job
     }
 
     pub fn add_MAQ(&self, job: Job) {
         let memory_alloc_queue = self.shared_state.get_memory_alloc_queue();
         let mut queue = memory_alloc_queue.lock().unwrap();
+        self.notify_queue("MemoryAllocQueue", QueueChange::Added, Some(&job));
         queue.add_job(job);
     }
     
+    // Under fixed partitions the first job whose partition is free goes
+    // ahead of the jobs still waiting for theirs
     pub fn remove_MAQ(&self) -> Option<Job> {
         let memory_alloc_queue = self.shared_state.get_memory_alloc_queue();
         let mut queue = memory_alloc_queue.lock().unwrap();
-        queue.remove_job()
+        let memory = self.shared_state.get_memory();
+        let mem = memory.lock().unwrap();
+        let job = match mem.allocator() {
+            MemoryAllocator::FixedPartitions(_) => queue.remove_first_job_where(|job| mem.can_alloc(job.memory_size)),
+            _ => None,
+        };
+        drop(mem);
+        let job = job.or_else(|| queue.remove_job());
+        self.notify_queue("MemoryAllocQueue", QueueChange::Removed, job.as_ref());
+        job
     }
 
     pub fn add_CAQ(&self, job: Job) {
         let cpu_alloc_queue = self.shared_state.get_cpu_alloc_queue();
         let mut queue = cpu_alloc_queue.lock().unwrap();
+        self.notify_queue("CPUAllocQueue", QueueChange::Added, Some(&job));
         queue.add_job(job);
     }
 
     pub fn remove_CAQ(&self) -> Option<Job> {
         let cpu_alloc_queue = self.shared_state.get_cpu_alloc_queue();
         let mut queue = cpu_alloc_queue.lock().unwrap();
-        queue.remove_job()
+        let job = queue.remove_job();
+        self.notify_queue("CPUAllocQueue", QueueChange::Removed, job.as_ref());
+        job
     }
 
     pub fn add_EQ(&self, job: Job) {
         let exec_queue = self.shared_state.get_exec_queue();
         let mut queue = exec_queue.lock().unwrap();
+        self.notify_queue("ExecQueue", QueueChange::Added, Some(&job));
         queue.add_job(job);
     }
 
     pub fn remove_EQ(&self) -> Option<Job> {
         let exec_queue = self.shared_state.get_exec_queue();
         let mut queue = exec_queue.lock().unwrap();
-        queue.remove_job()
+        let job = queue.remove_job();
+        self.notify_queue("ExecQueue", QueueChange::Removed, job.as_ref());
+        job
+    }
+
+    // Removes the job from every queue it is in, returning the
+    // names of the queues it was found in along with the job.
+    pub fn remove_from_queues(&self, job_id: i32) -> Vec<(&'static str, Job)> {
+        let mut removed = Vec::new();
+        if let Some(job) = self.shared_state.get_system_entry_queue().lock().unwrap().remove_job_by_id(job_id) {
+            removed.push(("SystemEntryQueue", job));
+        }
+        if let Some(job) = self.shared_state.get_memory_alloc_queue().lock().unwrap().remove_job_by_id(job_id) {
+            removed.push(("MemoryAllocQueue", job));
+        }
+        if let Some(job) = self.shared_state.get_cpu_alloc_queue().lock().unwrap().remove_job_by_id(job_id) {
+            removed.push(("CPUAllocQueue", job));
+        }
+        if let Some(job) = self.shared_state.get_exec_queue().lock().unwrap().remove_job_by_id(job_id) {
+            removed.push(("ExecQueue", job));
+        }
+        if let Some(job) = self.shared_state.get_blocked_queue().lock().unwrap().remove_job_by_id(job_id) {
+            removed.push(("BlockedQueue", job));
+        }
+        for (queue, job) in &removed {
+            self.notify_queue(queue, QueueChange::Removed, Some(job));
+        }
+        removed
+    }
+
+    // Removes every pending event that refers to the job, except the
+    // end of a disk access in course: the disk finishes it anyway and
+    // then moves on to the next request
+    pub fn cancel_job_events(&self, job_id: i32) -> Vec<Event> {
+        let event_list = self.shared_state.get_event_list();
+        let mut list = event_list.lock().unwrap();
+        list.remove_where(|event| {
+            event.metadata.job_id() == Some(job_id) && !matches!(event.metadata, Metadata::EndDiskAccess(_))
+        })
+    }
+
+    // Schedules the kill of a job for the current timestep, so it is
+    // handled by the next run of the event loop
+    pub fn kill_job(&self, job_id: i32) {
+        self.schedule_now("Cancelamento de job".to_string(), Metadata::KillJob(job_id));
+    }
+
+    pub fn caq_is_empty(&self) -> bool {
+        let cpu_alloc_queue = self.shared_state.get_cpu_alloc_queue();
+        let queue = cpu_alloc_queue.lock().unwrap();
+        queue.is_empty()
+    }
+
+    // Whether a job is running or one is already on its way to the
+    // processor in the current instant: events scheduled now run
+    // after the ones already due, so a job admitted or woken up
+    // earlier in the instant may not have reached the processor yet.
+    // A job being loaded claims it until the end of the loading.
+    pub fn processor_claimed(&self) -> bool {
+        let now = self.get_current_timestep();
+        let event_list = self.shared_state.get_event_list();
+        let list = event_list.lock().unwrap();
+        !self.eq_is_empty()
+            || list.iter().any(|event| match event.metadata {
+                Metadata::RequestCPU(_) => true,
+                Metadata::JobEntrance(_) | Metadata::RequestMemory(_) => event.time <= now,
+                _ => false,
+            })
     }
 
     pub fn eq_is_empty(&self) -> bool {
@@ -395,26 +1252,146 @@ impl ControlModule {
         let mut mem = memory.lock().unwrap();
         println!("Memoria livre restante: {}k", mem.available_memory());
         let result = mem.alloc(job.clone(), num);
-        match result {
-            Ok(_) => println!(""),
+        let usage = mem.usage();
+        drop(mem);
+        match &result {
+            Ok(segment) => {
+                println!();
+                let now = self.get_current_timestep();
+                self.record_memory_usage(now, usage);
+                let registers = self.shared_state.get_registers();
+                registers.lock().unwrap().insert(job.id, BaseLimit { base: segment.start_address(), limit: segment.size() });
+                self.notify(|observer| observer.memory_allocated(now, job.id, segment));
+            }
             Err(error) => println!("Memory allocation failed: {}", error),
         }
         result
     }
 
-    pub fn dealloc_memory(&self, job: Job) {
+    pub fn dealloc_memory(&self, job: Job) -> i32 {
         let memory = self.shared_state.get_memory();
         let mut mem = memory.lock().unwrap();
         println!("Memoria livre disponivel: {}k", mem.available_memory());
-        mem.dealloc(job.clone());
+        let freed = mem.dealloc(job.clone());
+        let usage = mem.usage();
+        drop(mem);
+        self.shared_state.get_registers().lock().unwrap().remove(&job.id);
+        self.shared_state.get_machines().lock().unwrap().remove(&job.id);
+        let now = self.get_current_timestep();
+        self.record_memory_usage(now, usage);
+        self.notify(|observer| observer.memory_freed(now, job.id, freed));
+        freed
+    }
+
+    pub fn registers(&self, job_id: i32) -> Option<BaseLimit> {
+        let registers = self.shared_state.get_registers();
+        let registers = registers.lock().unwrap();
+        registers.get(&job_id).copied()
     }
 
-    pub fn get_current_timestep(&self) -> i32 {
-        let current_timestep = self.shared_state.current_timestep;
-        current_timestep
+    // Physical address of a logical address of the job, or None if the
+    // job is not in memory
+    pub fn translate_address(&self, job_id: i32, address: i32) -> Option<Result<i32, ProtectionFault>> {
+        let result = self.registers(job_id)?.translate(address);
+        let statistics = self.shared_state.get_statistics();
+        statistics.lock().unwrap().record_memory_access(job_id, result.err());
+        Some(result)
+    }
+
+    pub fn set_machine(&self, job_id: i32, machine: Machine) {
+        let machines = self.shared_state.get_machines();
+        let mut machines = machines.lock().unwrap();
+        machines.insert(job_id, machine);
+    }
+
+    // The loader: copies the program of the job to the start of its
+    // segment, with the base added to every address field. Returns the
+    // time the loading takes, or None if the job has no program or is
+    // not in memory
+    pub fn load_program(&self, job_id: i32) -> Option<SimDuration> {
+        let registers = self.registers(job_id)?;
+        let machines = self.shared_state.get_machines();
+        let mut machines = machines.lock().unwrap();
+        let machine = machines.get_mut(&job_id)?;
+        let words = machine.image().relocate(registers.base);
+        let relocated = machine.image().relocations.len();
+        self.shared_state.get_memory().lock().unwrap().load(registers.base, &words);
+        machine.start(registers.base);
+        drop(machines);
+
+        let detail = format!(
+            "job {}: {} palavras no endereco {}, {} enderecos relocados",
+            job_id,
+            words.len(),
+            registers.base,
+            relocated
+        );
+        println!("Loader carregou o programa do {}", detail);
+        self.record_trace("Carga de programa".to_string(), detail);
+        Some(self.shared_state.load_time_per_word * words.len() as i64)
+    }
+
+    // Runs a copy of the program of the job, if it has one and is in
+    // memory, on a copy of its segment for at most the given time from
+    // now, to find out when it gives the processor back. Nothing is
+    // written to the memory or recorded: the instructions run in
+    // finish_program_slice, once the simulation reaches the end of the
+    // slice.
+    pub fn start_program_slice(&self, job_id: i32, time: SimDuration) -> Option<Execution> {
+        let registers = self.registers(job_id)?;
+        let mut machine = self.shared_state.get_machines().lock().unwrap().get(&job_id)?.clone();
+        let mut segment = SegmentCopy::new(&self.shared_state.get_memory().lock().unwrap(), registers);
+        let execution = machine.run(time, registers, &mut segment);
+
+        let now = self.get_current_timestep();
+        self.shared_state.get_program_slices().lock().unwrap().insert(job_id, ProgramSlice { start: now, stop_event: None });
+        Some(execution)
+    }
+
+    // Records the event scheduled for the instant the program of the
+    // job stops within its time slice
+    pub fn set_program_stop(&self, job_id: i32, handle: EventHandle) {
+        if let Some(slice) = self.shared_state.get_program_slices().lock().unwrap().get_mut(&job_id) {
+            slice.stop_event = Some(handle);
+        }
     }
 
-    pub fn update_current_timestep(&mut self, current_timestep: i32) {
+    // Runs the program of the job from the start of its time slice up
+    // to now, when the slice ends or is cut short. A slice cut short
+    // drops the event of the stop of the program, if it has one.
+    // Returns None if the job was not running a program.
+    pub fn finish_program_slice(&self, job_id: i32) -> Option<Execution> {
+        let ProgramSlice { start, stop_event } = self.shared_state.get_program_slices().lock().unwrap().remove(&job_id)?;
+        if let Some(handle) = stop_event {
+            self.cancel_event(handle);
+        }
+        let registers = self.registers(job_id)?;
+        let time = self.get_current_timestep() - start;
+        let machines = self.shared_state.get_machines();
+        let mut machines = machines.lock().unwrap();
+        let machine = machines.get_mut(&job_id)?;
+        let execution = machine.run(time, registers, &mut *self.shared_state.get_memory().lock().unwrap());
+        drop(machines);
+
+        for (elapsed, value) in &execution.output {
+            println!("Saida do job {} no instante {}: {}", job_id, start + *elapsed, value);
+        }
+        let statistics = self.shared_state.get_statistics();
+        statistics.lock().unwrap().record_program_run(job_id, start, &execution);
+        Some(execution)
+    }
+
+    fn record_memory_usage(&self, time: SimTime, usage: MemoryUsage) {
+        let statistics = self.shared_state.get_statistics();
+        let mut stats = statistics.lock().unwrap();
+        stats.record_memory_usage(time, usage);
+    }
+
+    pub fn get_current_timestep(&self) -> SimTime {
+        self.shared_state.current_timestep
+    }
+
+    pub fn update_current_timestep(&mut self, current_timestep: SimTime) {
         self.shared_state.current_timestep = current_timestep;
     }
 
@@ -430,13 +1407,13 @@ impl ControlModule {
         queue.is_empty()
     }
 
-    pub fn add_to_job_table(&self, id: i32, cpu_time: i32) {
+    pub fn add_to_job_table(&self, id: i32, cpu_time: SimDuration) {
         let job_table = self.shared_state.get_job_table();
         let mut table = job_table.lock().unwrap();
         table.add_job(id, cpu_time);
     }
 
-    pub fn update_job_table(&self, id: i32, time_slice: i32) {
+    pub fn update_job_table(&self, id: i32, time_slice: SimDuration) {
         let job_table = self.shared_state.get_job_table();
         let mut table = job_table.lock().unwrap();
         table.pause_job(id, time_slice);
@@ -448,20 +1425,33 @@ impl ControlModule {
         table.delete_job(id);
     }
 
-    pub fn get_time_remaining(&self, id: i32) -> i32 {
+    pub fn set_time_remaining(&self, id: i32, time_remaining: SimDuration) {
         let job_table = self.shared_state.get_job_table();
         let mut table = job_table.lock().unwrap();
+        table.add_job(id, time_remaining);
+    }
+
+    // None if the job has not had the processor yet
+    pub fn get_time_remaining(&self, id: i32) -> Option<SimDuration> {
+        let job_table = self.shared_state.get_job_table();
+        let table = job_table.lock().unwrap();
         table.get_time_remaining(id)
     }
 
+    // Processing time a job gets each time it is handed the
+    // processor; under FCFS jobs are never preempted
+    pub fn time_slice(&self) -> SimDuration {
+        self.shared_state.time_slice
+    }
+
+    pub fn multiprogramming_level(&self) -> i32 {
+        self.shared_state.multiprogramming_level
+    }
+
     pub fn table_is_full(&self, max_jobs: i32) -> bool {
         let job_table = self.shared_state.get_job_table();
-        let mut table = job_table.lock().unwrap();
-        if table.len() < max_jobs {
-            return false;
-        } else {
-            return true;
-        }
+        let table = job_table.lock().unwrap();
+        table.len() >= max_jobs
     }
 
     pub fn job_exists_in_table(&self, job_id: i32) -> bool {
@@ -469,4 +1459,666 @@ impl ControlModule {
         let table = job_table.lock().unwrap();
         table.job_exists(job_id)
     }
+
+    // Takes the job off the processor, or out of the cpu alloc queue,
+    // and parks it in the blocked queue. A running job has its time
+    // slice cut short: the processing time it has left is kept in
+    // the job table and the processor goes to the next job of the
+    // cpu alloc queue. Returns false if the job was neither running
+    // nor waiting for the processor.
+    pub fn block_job(&self, job_id: i32, reason: &str) -> bool {
+        let now = self.get_current_timestep();
+        let running = self.shared_state.get_exec_queue().lock().unwrap().remove_job_by_id(job_id);
+        self.notify_queue("ExecQueue", QueueChange::Removed, running.as_ref());
+
+        let job = if let Some(job) = running {
+            let execution = self.finish_program_slice(job_id);
+            let cancelled = {
+                let event_list = self.shared_state.get_event_list();
+                let mut list = event_list.lock().unwrap();
+                list.remove_where(|event| {
+                    event.metadata.job_id() == Some(job_id)
+                        && matches!(
+                            event.metadata,
+                            Metadata::PauseJob(_) | Metadata::EndProcess(_) | Metadata::StartIo(_, _)
+                        )
+                })
+            };
+            // The end of the slice of a program may have been moved to
+            // the stop of the program, so what the program has left
+            // comes from the time its instructions took
+            let time_remaining = self.get_time_remaining(job_id).map(|time_remaining| match execution {
+                Some(execution) => time_remaining - execution.elapsed,
+                None => cancelled.iter().fold(time_remaining, |time_remaining, event| match event.metadata {
+                    Metadata::PauseJob(_) => time_remaining - (self.time_slice() - (event.time - now)),
+                    Metadata::StartIo(_, cpu_used) => time_remaining - (cpu_used - (event.time - now)),
+                    _ => event.time - now,
+                }),
+            });
+            if let Some(time_remaining) = time_remaining {
+                self.set_time_remaining(job_id, time_remaining);
+            }
+            if let Some(next_job) = self.remove_CAQ() {
+                self.schedule_now("Requisicao de processador de job".to_string(), Metadata::RequestCPU(next_job));
+            }
+            job
+        } else if let Some(job) = self.shared_state.get_cpu_alloc_queue().lock().unwrap().remove_job_by_id(job_id) {
+            self.notify_queue("CPUAllocQueue", QueueChange::Removed, Some(&job));
+            job
+        } else {
+            return false;
+        };
+
+        println!("Job {} bloqueado: {}", job_id, reason);
+        self.notify_queue("BlockedQueue", QueueChange::Added, Some(&job));
+        self.shared_state.get_blocked_queue().lock().unwrap().add_job(job, reason.to_string());
+        self.record_trace(format!("Bloqueio de job {}", job_id), reason.to_string());
+        self.shared_state.get_statistics().lock().unwrap().record_block(job_id, now);
+        true
+    }
+
+    // Moves a blocked job back to the processor: it runs right away
+    // if the processor is idle, otherwise it waits in the cpu alloc
+    // queue. A job already on its way to the processor counts as the
+    // processor being taken, so jobs woken up together do not all
+    // get it.
+    // Returns false if the job was not blocked.
+    pub fn unblock_job(&self, job_id: i32) -> bool {
+        let job = self.shared_state.get_blocked_queue().lock().unwrap().remove_job_by_id(job_id);
+        let Some(job) = job else {
+            return false;
+        };
+        self.notify_queue("BlockedQueue", QueueChange::Removed, Some(&job));
+
+        println!("Job {} desbloqueado", job_id);
+        if !self.processor_claimed() {
+            self.schedule_now("Requisicao de processador de job".to_string(), Metadata::RequestCPU(job));
+        } else {
+            self.add_CAQ(job);
+        }
+        self.record_trace(format!("Desbloqueio de job {}", job_id), String::new());
+        self.shared_state.get_statistics().lock().unwrap().record_unblock(job_id, self.get_current_timestep());
+        true
+    }
+
+    pub fn is_blocked(&self, job_id: i32) -> bool {
+        let blocked_queue = self.shared_state.get_blocked_queue();
+        let queue = blocked_queue.lock().unwrap();
+        queue.contains(job_id)
+    }
+
+    pub fn declare_resources(&self, job_id: i32, claim: Vec<i32>) -> Result<(), ResourceError> {
+        let resource_manager = self.shared_state.get_resource_manager();
+        let mut manager = resource_manager.lock().unwrap();
+        manager.declare(job_id, claim)
+    }
+
+    // Asks the resource manager for units; a request that cannot be
+    // granted blocks the job until it is
+    pub fn request_resources(&self, job_id: i32, request: Vec<i32>) -> Result<RequestOutcome, SimulationError> {
+        let outcome = {
+            let resource_manager = self.shared_state.get_resource_manager();
+            let mut manager = resource_manager.lock().unwrap();
+            manager.request(job_id, request)?
+        };
+        if outcome != RequestOutcome::Granted {
+            let reason = match outcome {
+                RequestOutcome::Unsafe => "recursos: estado inseguro",
+                _ => "recursos: indisponiveis",
+            };
+            if !self.block_job(job_id, reason) {
+                self.shared_state.get_resource_manager().lock().unwrap().withdraw_request(job_id);
+                return Err(SimulationError::JobNotRunning(job_id));
+            }
+        }
+        self.shared_state.get_statistics().lock().unwrap().record_resource_request(&outcome);
+        Ok(outcome)
+    }
+
+    pub fn release_resources(&self, job_id: i32, release: Vec<i32>) -> Result<(), ResourceError> {
+        {
+            let resource_manager = self.shared_state.get_resource_manager();
+            let mut manager = resource_manager.lock().unwrap();
+            manager.release(job_id, release)?;
+        }
+        self.grant_waiting_resources();
+        Ok(())
+    }
+
+    // Called when the job leaves the system
+    pub fn release_all_resources(&self, job_id: i32) {
+        {
+            let resource_manager = self.shared_state.get_resource_manager();
+            let mut manager = resource_manager.lock().unwrap();
+            manager.release_all(job_id);
+        }
+        self.grant_waiting_resources();
+    }
+
+    pub fn resource_policy(&self) -> ResourcePolicy {
+        let resource_manager = self.shared_state.get_resource_manager();
+        let manager = resource_manager.lock().unwrap();
+        manager.policy()
+    }
+
+    // Whether some job is waiting to enter memory or is in it
+    pub fn jobs_in_system(&self) -> bool {
+        !self.seq_is_empty() || !self.maq_is_empty() || self.shared_state.get_memory().lock().unwrap().has_jobs()
+    }
+
+    // Whether some job in the system uses resources now or a job will
+    // declare its claims later, i.e. whether deadlocks can still happen
+    pub fn resources_in_use(&self) -> bool {
+        if self.shared_state.get_resource_manager().lock().unwrap().has_jobs() && self.jobs_in_system() {
+            return true;
+        }
+        let event_list = self.shared_state.get_event_list();
+        let list = event_list.lock().unwrap();
+        list.iter().any(|event| matches!(event.metadata, Metadata::DeclareResources(..)))
+    }
+
+    // Deadlocked jobs and the wait-for graph of the jobs waiting for
+    // resources
+    pub fn detect_deadlock(&self) -> (Vec<i32>, BTreeMap<i32, Vec<i32>>) {
+        let resource_manager = self.shared_state.get_resource_manager();
+        let manager = resource_manager.lock().unwrap();
+        (manager.detect_deadlock(), manager.wait_for_graph())
+    }
+
+    // Takes the resources of a job and rolls it back to its start:
+    // it waits, blocked, for everything it had and its whole
+    // processing time is due again. The units go to the jobs waiting
+    // for them, the job itself included.
+    pub fn preempt_resources(&self, job_id: i32) -> Vec<i32> {
+        let taken = {
+            let resource_manager = self.shared_state.get_resource_manager();
+            let mut manager = resource_manager.lock().unwrap();
+            manager.preempt(job_id)
+        };
+
+        if !self.is_blocked(job_id) {
+            self.block_job(job_id, "recursos: retomados");
+        }
+        let cpu_time = {
+            let blocked_queue = self.shared_state.get_blocked_queue();
+            let queue = blocked_queue.lock().unwrap();
+            queue.jobs().iter().find(|(job, _)| job.id == job_id).map(|(job, _)| job.cpu_time)
+        };
+        if let Some(cpu_time) = cpu_time {
+            if self.job_exists_in_table(job_id) {
+                self.set_time_remaining(job_id, cpu_time);
+            }
+        }
+
+        self.grant_waiting_resources();
+        taken
+    }
+
+    fn grant_waiting_resources(&self) {
+        let granted = {
+            let resource_manager = self.shared_state.get_resource_manager();
+            let mut manager = resource_manager.lock().unwrap();
+            manager.grant_waiting()
+        };
+        for job_id in granted {
+            println!("Requisicao de recursos do job {} atendida", job_id);
+            self.unblock_job(job_id);
+        }
+    }
+
+    // Waits on a semaphore or mutex; if it is not available the job
+    // is blocked until a signal wakes it up. Returns whether the job
+    // got the object right away.
+    pub fn semaphore_wait(&self, job_id: i32, name: &str) -> Result<bool, SimulationError> {
+        let acquired = {
+            let sync_manager = self.shared_state.get_sync_manager();
+            let mut manager = sync_manager.lock().unwrap();
+            manager.wait(name, job_id)?
+        };
+        if !acquired && !self.block_job(job_id, &format!("sincronizacao: {}", name)) {
+            self.shared_state.get_sync_manager().lock().unwrap().cancel_wait(name, job_id);
+            return Err(SimulationError::JobNotRunning(job_id));
+        }
+        self.shared_state.get_statistics().lock().unwrap().record_sync_wait(acquired);
+        Ok(acquired)
+    }
+
+    // Signals a semaphore or mutex. The job woken up, if any, gets a
+    // wake-up event to be immediately treated.
+    pub fn semaphore_signal(&self, job_id: i32, name: &str) -> Result<Option<i32>, SyncError> {
+        let woken = {
+            let sync_manager = self.shared_state.get_sync_manager();
+            let mut manager = sync_manager.lock().unwrap();
+            manager.signal(name, job_id)?
+        };
+        self.shared_state.get_statistics().lock().unwrap().record_sync_signal();
+        if let Some(woken_job) = woken {
+            self.schedule_now("Despertar de job".to_string(), Metadata::WakeJob(woken_job, name.to_string()));
+        }
+        Ok(woken)
+    }
+
+    // Called when the job leaves the system: it stops waiting and the
+    // mutexes it holds are passed on
+    pub fn release_synchronization(&self, job_id: i32) {
+        let woken = {
+            let sync_manager = self.shared_state.get_sync_manager();
+            let mut manager = sync_manager.lock().unwrap();
+            manager.remove_job(job_id)
+        };
+        for (name, woken_job) in woken {
+            self.schedule_now("Despertar de job".to_string(), Metadata::WakeJob(woken_job, name));
+        }
+    }
+
+    // Sends a message through the channel. If the buffer is full the
+    // sender is blocked until a receiver makes room. Returns whether
+    // the message went into the buffer.
+    pub fn send_message(&self, job_id: i32, channel: &str, size: i32) -> Result<bool, SimulationError> {
+        let now = self.get_current_timestep();
+        let ready_at = {
+            let channel_manager = self.shared_state.get_channel_manager();
+            let mut manager = channel_manager.lock().unwrap();
+            manager.send(channel, job_id, size, now)?
+        };
+        self.shared_state.get_statistics().lock().unwrap().record_channel_send(channel, now);
+        match ready_at {
+            Some(ready_at) => {
+                self.schedule_at(ready_at, "Entrega de mensagem".to_string(), Metadata::DeliverMessage(channel.to_string()));
+                Ok(true)
+            }
+            None => {
+                if !self.block_job(job_id, &format!("canal {}: buffer cheio", channel)) {
+                    self.shared_state.get_channel_manager().lock().unwrap().cancel_wait(channel, job_id);
+                    return Err(SimulationError::JobNotRunning(job_id));
+                }
+                Ok(false)
+            }
+        }
+    }
+
+    // Receives the oldest message available in the channel. With no
+    // message ready the receiver is blocked until one is delivered.
+    // Returns the size of the message received.
+    pub fn receive_message(&self, job_id: i32, channel: &str) -> Result<Option<i32>, SimulationError> {
+        let now = self.get_current_timestep();
+        let message = {
+            let channel_manager = self.shared_state.get_channel_manager();
+            let mut manager = channel_manager.lock().unwrap();
+            manager.receive(channel, job_id, now)?
+        };
+        match message {
+            Some(message) => {
+                self.shared_state.get_statistics().lock().unwrap().record_channel_receive(
+                    channel,
+                    message.size,
+                    message.sent_at,
+                    now,
+                );
+                self.admit_blocked_senders(channel)?;
+                Ok(Some(message.size))
+            }
+            None => {
+                if !self.block_job(job_id, &format!("canal {}: sem mensagens", channel)) {
+                    self.shared_state.get_channel_manager().lock().unwrap().cancel_wait(channel, job_id);
+                    return Err(SimulationError::JobNotRunning(job_id));
+                }
+                Ok(None)
+            }
+        }
+    }
+
+    // Hands the messages that became available to the receivers
+    // waiting on the channel, waking them up. Returns the receivers.
+    pub fn deliver_messages(&self, channel: &str) -> Result<Vec<i32>, ChannelError> {
+        let now = self.get_current_timestep();
+        let delivered = {
+            let channel_manager = self.shared_state.get_channel_manager();
+            let mut manager = channel_manager.lock().unwrap();
+            manager.deliver(channel, now)?
+        };
+        let mut receivers = Vec::new();
+        for (receiver, message, since) in delivered {
+            {
+                let statistics = self.shared_state.get_statistics();
+                let mut stats = statistics.lock().unwrap();
+                stats.record_channel_receive(channel, message.size, message.sent_at, now);
+                stats.record_channel_receiver_wait(channel, now - since);
+            }
+            self.schedule_now("Despertar de job".to_string(), Metadata::WakeJob(receiver, channel.to_string()));
+            receivers.push(receiver);
+        }
+        if !receivers.is_empty() {
+            self.admit_blocked_senders(channel)?;
+        }
+        Ok(receivers)
+    }
+
+    // Moves the messages of blocked senders into the room freed in
+    // the channel buffer, waking the senders up
+    fn admit_blocked_senders(&self, channel: &str) -> Result<(), ChannelError> {
+        let now = self.get_current_timestep();
+        let admitted = {
+            let channel_manager = self.shared_state.get_channel_manager();
+            let mut manager = channel_manager.lock().unwrap();
+            manager.admit_senders(channel, now)?
+        };
+        for (sender, ready_at, since) in admitted {
+            self.shared_state.get_statistics().lock().unwrap().record_channel_sender_wait(channel, now - since);
+            self.schedule_at(ready_at, "Entrega de mensagem".to_string(), Metadata::DeliverMessage(channel.to_string()));
+            self.schedule_now("Despertar de job".to_string(), Metadata::WakeJob(sender, channel.to_string()));
+        }
+        Ok(())
+    }
+
+    // Called when the job leaves the system: it stops waiting on
+    // every channel
+    pub fn release_channels(&self, job_id: i32) {
+        let channel_manager = self.shared_state.get_channel_manager();
+        let mut manager = channel_manager.lock().unwrap();
+        manager.remove_job(job_id);
+    }
+
+    // Runs an operation on the file system and records its access
+    // cost and the fragmentation it leaves
+    pub fn file_operation<F>(&self, operation: F) -> Result<i32, FileSystemError>
+    where
+        F: FnOnce(&mut FileSystem) -> Result<i32, FileSystemError>,
+    {
+        let file_system = self.shared_state.get_file_system();
+        let mut fs = file_system.lock().unwrap();
+        let result = operation(&mut fs);
+        self.shared_state.get_statistics().lock().unwrap().record_file_operation(
+            result.as_ref().ok().copied(),
+            fs.strategy(),
+            fs.fragmentation(),
+        );
+        result
+    }
+
+    // Queues a disk request. If the disk is idle, its next access
+    // starts once every event of this instant has been treated, so
+    // requests issued together are ordered by the disk policy.
+    pub fn request_disk(&self, job_id: i32, cylinder: i32) {
+        let now = self.get_current_timestep();
+        let was_idle = {
+            let disk = self.shared_state.get_disk();
+            let mut disk = disk.lock().unwrap();
+            disk.submit(DiskRequest { job_id, cylinder, arrival: now })
+        };
+        if was_idle {
+            self.schedule_now("Inicio de acesso ao disco".to_string(), Metadata::StartDiskAccess);
+        }
+    }
+
+    // Drops the disk requests the job still has waiting
+    pub fn cancel_disk_requests(&self, job_id: i32) -> usize {
+        let disk = self.shared_state.get_disk();
+        let mut disk = disk.lock().unwrap();
+        disk.cancel_requests(job_id)
+    }
+
+    // Moves the arm to the next request picked by the disk policy and
+    // schedules the end of the access. Returns the request started.
+    pub fn start_disk_access(&self) -> Option<DiskRequest> {
+        let next = {
+            let disk = self.shared_state.get_disk();
+            let mut disk = disk.lock().unwrap();
+            disk.start_next()
+        };
+        let (request, access_time) = next?;
+        self.schedule_after(
+            access_time,
+            "Fim de acesso ao disco".to_string(),
+            Metadata::EndDiskAccess(request.job_id),
+        );
+        Some(request)
+    }
+
+    // Ends the access in course, recording its seek distance and
+    // response time, and starts the next one
+    pub fn end_disk_access(&self) -> Option<DiskRequest> {
+        let now = self.get_current_timestep();
+        let (served, policy) = {
+            let disk = self.shared_state.get_disk();
+            let mut disk = disk.lock().unwrap();
+            (disk.complete(), disk.policy())
+        };
+        let (request, distance) = served?;
+        self.shared_state.get_statistics().lock().unwrap().record_disk_access(policy, distance, now - request.arrival);
+        self.start_disk_access();
+        Some(request)
+    }
+
+    pub fn set_job_profile(&self, job_id: i32, profile: JobProfile) {
+        let job_profiles = self.shared_state.get_job_profiles();
+        let mut profiles = job_profiles.lock().unwrap();
+        profiles.insert(job_id, profile);
+    }
+
+    // Processing time the job has before its next I/O burst, if that
+    // burst comes within a time slice and before the job ends
+    pub fn cpu_until_io(&self, job_id: i32, time_slice: SimDuration) -> Option<SimDuration> {
+        let time_remaining = self.get_time_remaining(job_id)?;
+        let job_profiles = self.shared_state.get_job_profiles();
+        let profiles = job_profiles.lock().unwrap();
+        let profile = profiles.get(&job_id)?;
+        let (cpu_until_io, _, _) = profile.next_io(profile.cpu_time() - time_remaining)?;
+        (cpu_until_io < time_remaining && cpu_until_io <= time_slice).then_some(cpu_until_io)
+    }
+
+    // Takes the job off the processor for its next I/O burst. Devices
+    // serve one burst at a time, in order of request. Returns the
+    // device and the instant the burst ends.
+    pub fn start_io(&self, job: Job, cpu_used: SimDuration) -> Option<(String, SimTime)> {
+        let now = self.get_current_timestep();
+        let time_remaining = self.get_time_remaining(job.id)?;
+        let (device, io_time) = {
+            let job_profiles = self.shared_state.get_job_profiles();
+            let mut profiles = job_profiles.lock().unwrap();
+            let profile = profiles.get_mut(&job.id)?;
+            let (_, device, io_time) = profile.next_io(profile.cpu_time() - time_remaining)?;
+            profile.start_io();
+            (device, io_time)
+        };
+        self.set_time_remaining(job.id, time_remaining - cpu_used);
+
+        let end = {
+            let io_devices = self.shared_state.get_io_devices();
+            let mut devices = io_devices.lock().unwrap();
+            let free_at = devices.entry(device.clone()).or_insert(now);
+            *free_at = (*free_at).max(now) + io_time;
+            *free_at
+        };
+        self.block_job(job.id, &format!("E/S: {}", device));
+        self.shared_state.get_statistics().lock().unwrap().record_io_burst(end - now);
+        self.schedule_at(end, "Fim de E/S de job".to_string(), Metadata::EndIo(job));
+        Some((device, end))
+    }
+
+    // With spooling, queues the deck of an arriving job on the card
+    // reader and returns the instant it is all in the spool area
+    pub fn spool_input(&self, job: &Job) -> Option<SimTime> {
+        let now = self.get_current_timestep();
+        let (cards, end, peak) = {
+            let spool = self.shared_state.get_spool();
+            let mut spool = spool.lock().unwrap();
+            let spool = spool.as_mut()?;
+            let (cards, end) = spool.read_deck(job.id, now);
+            (cards, end, spool.peak())
+        };
+        let statistics = self.shared_state.get_statistics();
+        statistics.lock().unwrap().record_card_read(cards, end - now, peak);
+        self.schedule_at(end, "Fim de leitura de cartoes de job".to_string(), Metadata::EndCardRead(job.clone()));
+        Some(end)
+    }
+
+    // The deck of the job entering the system, or killed before
+    // entering it, leaves the spool area. Returns whether it was there.
+    pub fn load_spooled_deck(&self, job_id: i32) -> bool {
+        let spool = self.shared_state.get_spool();
+        let mut spool = spool.lock().unwrap();
+        spool.as_mut().is_some_and(|spool| spool.load_deck(job_id))
+    }
+
+    // With spooling, moves the output of a job leaving the system to
+    // the spool area and queues it on the printer. Returns the
+    // instant printing ends.
+    pub fn spool_output(&self, job: &Job) -> Option<SimTime> {
+        let now = self.get_current_timestep();
+        let (lines, end, peak) = {
+            let spool = self.shared_state.get_spool();
+            let mut spool = spool.lock().unwrap();
+            let spool = spool.as_mut()?;
+            let (lines, end) = spool.print(job.id, now);
+            (lines, end, spool.peak())
+        };
+        self.shared_state.get_statistics().lock().unwrap().record_print(lines, end - now, peak);
+        self.schedule_at(end, "Fim de impressao de job".to_string(), Metadata::EndPrint(job.clone()));
+        Some(end)
+    }
+
+    pub fn end_print(&self, job_id: i32) {
+        if let Some(spool) = self.shared_state.get_spool().lock().unwrap().as_mut() {
+            spool.end_print(job_id);
+        }
+    }
+
+    pub fn set_job_priority(&self, job_id: i32, priority: i32) {
+        let job_priorities = self.shared_state.get_job_priorities();
+        let mut priorities = job_priorities.lock().unwrap();
+        priorities.insert(job_id, priority);
+    }
+
+    // Jobs without an explicit priority have priority 0; higher
+    // values are more important
+    pub fn get_job_priority(&self, job_id: i32) -> i32 {
+        let job_priorities = self.shared_state.get_job_priorities();
+        let priorities = job_priorities.lock().unwrap();
+        priorities.get(&job_id).copied().unwrap_or(0)
+    }
+
+    pub fn record_trace(&self, name: String, detail: String) {
+        let trace = self.shared_state.get_trace();
+        let mut trace = trace.lock().unwrap();
+        trace.record(self.shared_state.current_timestep, name, detail);
+    }
+
+    pub fn record_event_statistics(&self) {
+        let statistics = self.shared_state.get_statistics();
+        let mut statistics = statistics.lock().unwrap();
+        statistics.record_event(self.shared_state.current_timestep);
+    }
+
+    pub fn record_job_arrival(&self, job_id: i32) {
+        let statistics = self.shared_state.get_statistics();
+        let mut statistics = statistics.lock().unwrap();
+        statistics.record_arrival(job_id, self.shared_state.current_timestep);
+    }
+
+    pub fn record_job_exit(&self, job_id: i32) {
+        let statistics = self.shared_state.get_statistics();
+        let mut statistics = statistics.lock().unwrap();
+        statistics.record_exit(job_id, self.shared_state.current_timestep);
+    }
+
+    pub fn record_deadlock(&self, victim: i32) {
+        let statistics = self.shared_state.get_statistics();
+        let mut statistics = statistics.lock().unwrap();
+        statistics.record_deadlock(victim);
+    }
+
+    pub fn record_deck_rejection(&self) {
+        let statistics = self.shared_state.get_statistics();
+        let mut statistics = statistics.lock().unwrap();
+        statistics.record_deck_rejection();
+    }
+
+    pub fn record_job_kill(&self, job_id: i32) {
+        let statistics = self.shared_state.get_statistics();
+        let mut statistics = statistics.lock().unwrap();
+        statistics.record_kill(job_id, self.shared_state.current_timestep);
+    }
+
+    // Snapshot of the queues, memory and job table, used to
+    // report the state of the system when a routine fails.
+    pub fn describe_state(&self) -> String {
+        let memory = self.shared_state.get_memory();
+        let mem = memory.lock().unwrap();
+        format!(
+            "Instante atual: {}\n{:?}\n{:?}\n{:?}\n{:?}\n{:?}\nMemoria livre: {}k, segmentos: {:?}\n{:?}\n{:?}",
+            self.shared_state.current_timestep,
+            self.shared_state.get_system_entry_queue().lock().unwrap(),
+            self.shared_state.get_memory_alloc_queue().lock().unwrap(),
+            self.shared_state.get_cpu_alloc_queue().lock().unwrap(),
+            self.shared_state.get_exec_queue().lock().unwrap(),
+            self.shared_state.get_blocked_queue().lock().unwrap(),
+            mem.available_memory(),
+            mem.segments,
+            self.shared_state.get_job_table().lock().unwrap(),
+            self.shared_state.get_resource_manager().lock().unwrap(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn job(id: i32, memory_size: i32) -> Job {
+        Job { id, state: 2, memory_size, cpu_time: SimDuration::from(10) }
+    }
+
+    #[test]
+    fn test_buddy_allocator_splits_and_coalesces() {
+        let mut memory = Memory::with_allocator(128, MemoryAllocator::Buddy, PlacementStrategy::FirstFit);
+
+        // 30 is rounded up to 32, splitting 128 into 64, 32 and 32
+        let first = memory.alloc(job(1, 30), 30).unwrap();
+        assert_eq!((first.start_address(), first.block_size()), (0, 32));
+        let second = memory.alloc(job(2, 60), 60).unwrap();
+        assert_eq!((second.start_address(), second.block_size()), (64, 64));
+        let usage = memory.usage();
+        assert_eq!(usage.holes, vec![32]);
+        assert_eq!(usage.internal_fragmentation, 2 + 4);
+        assert!(memory.alloc(job(3, 40), 40).is_err());
+
+        // Freeing both merges the blocks back into one of 128
+        assert_eq!(memory.dealloc(job(1, 30)), 32);
+        assert_eq!(memory.usage().holes, vec![64]);
+        memory.dealloc(job(2, 60));
+        assert_eq!(memory.usage().holes, vec![128]);
+        assert_eq!(memory.available_memory(), 128);
+
+        // A segment larger than the memory is refused before rounding,
+        // even where rounding would overflow
+        assert!(memory.can_alloc(128));
+        for size in [129, (1 << 30) + 1, i32::MAX] {
+            assert!(!memory.can_alloc(size));
+            assert!(memory.alloc(job(3, size), size).is_err());
+        }
+        assert_eq!(memory.available_memory(), 128);
+
+        // A size that is not a power of two starts as aligned blocks
+        let memory = Memory::with_allocator(100, MemoryAllocator::Buddy, PlacementStrategy::FirstFit);
+        assert_eq!(memory.usage().holes, vec![64, 32, 4]);
+    }
+
+    #[test]
+    fn test_fixed_partitions_route_jobs_by_size() {
+        for (queues, expected) in [(PartitionQueues::Single, Some(80)), (PartitionQueues::PerPartition, None)] {
+            let mut memory = Memory::with_partitions(&[30, 60, 80], queues);
+            let first = memory.alloc(job(1, 50), 50).unwrap();
+            assert_eq!((first.start_address(), first.block_size()), (30, 60));
+            assert_eq!(memory.usage().internal_fragmentation, 10);
+
+            // The 60k partition is taken: a single queue hands the job
+            // the 80k one, its own queue keeps it waiting for the 60k
+            let second = memory.alloc(job(2, 45), 45).ok().map(|segment| segment.block_size());
+            assert_eq!(second, expected);
+            assert!(!memory.can_alloc(45));
+            memory.dealloc(job(1, 50));
+            assert!(memory.can_alloc(45));
+        }
+    }
 }
@@ -0,0 +1,213 @@
+// In this file it is implemented the simulation time. A SimTime is
+// an instant of the simulation and a SimDuration the time between
+// two instants, both counted in ticks on a 64-bit range. Adding a
+// duration to an instant gives an instant, subtracting two instants
+// gives a duration; instants cannot be added together. The operators
+// panic if the result leaves the range, like the std time types, and
+// the checked_ methods return None instead.
+use std::fmt;
+use std::ops::{Add, AddAssign, Mul, Neg, Sub, SubAssign};
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct SimTime(i64);
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct SimDuration(i64);
+
+impl SimTime {
+    // Start of the simulation
+    pub const ZERO: SimTime = SimTime(0);
+    pub const MAX: SimTime = SimTime(i64::MAX);
+
+    pub const fn from_ticks(ticks: i64) -> Self {
+        SimTime(ticks)
+    }
+
+    pub const fn ticks(self) -> i64 {
+        self.0
+    }
+
+    pub fn checked_add(self, duration: SimDuration) -> Option<SimTime> {
+        self.0.checked_add(duration.0).map(SimTime)
+    }
+
+    pub fn checked_sub(self, duration: SimDuration) -> Option<SimTime> {
+        self.0.checked_sub(duration.0).map(SimTime)
+    }
+
+    // Time elapsed since an earlier instant
+    pub fn checked_duration_since(self, earlier: SimTime) -> Option<SimDuration> {
+        self.0.checked_sub(earlier.0).map(SimDuration)
+    }
+}
+
+impl SimDuration {
+    pub const ZERO: SimDuration = SimDuration(0);
+    pub const MAX: SimDuration = SimDuration(i64::MAX);
+
+    pub const fn from_ticks(ticks: i64) -> Self {
+        SimDuration(ticks)
+    }
+
+    pub const fn ticks(self) -> i64 {
+        self.0
+    }
+
+    pub fn checked_add(self, other: SimDuration) -> Option<SimDuration> {
+        self.0.checked_add(other.0).map(SimDuration)
+    }
+
+    pub fn checked_sub(self, other: SimDuration) -> Option<SimDuration> {
+        self.0.checked_sub(other.0).map(SimDuration)
+    }
+
+    pub fn checked_mul(self, factor: i64) -> Option<SimDuration> {
+        self.0.checked_mul(factor).map(SimDuration)
+    }
+}
+
+// Scenarios are written with plain tick counts
+impl From<i32> for SimTime {
+    fn from(ticks: i32) -> Self {
+        SimTime(ticks.into())
+    }
+}
+
+impl From<i32> for SimDuration {
+    fn from(ticks: i32) -> Self {
+        SimDuration(ticks.into())
+    }
+}
+
+const OVERFLOW: &str = "overflow no tempo de simulacao";
+
+impl Add<SimDuration> for SimTime {
+    type Output = SimTime;
+
+    fn add(self, duration: SimDuration) -> SimTime {
+        self.checked_add(duration).expect(OVERFLOW)
+    }
+}
+
+impl Sub<SimDuration> for SimTime {
+    type Output = SimTime;
+
+    fn sub(self, duration: SimDuration) -> SimTime {
+        self.checked_sub(duration).expect(OVERFLOW)
+    }
+}
+
+impl Sub<SimTime> for SimTime {
+    type Output = SimDuration;
+
+    fn sub(self, earlier: SimTime) -> SimDuration {
+        self.checked_duration_since(earlier).expect(OVERFLOW)
+    }
+}
+
+impl AddAssign<SimDuration> for SimTime {
+    fn add_assign(&mut self, duration: SimDuration) {
+        *self = *self + duration;
+    }
+}
+
+impl Add for SimDuration {
+    type Output = SimDuration;
+
+    fn add(self, other: SimDuration) -> SimDuration {
+        self.checked_add(other).expect(OVERFLOW)
+    }
+}
+
+impl Sub for SimDuration {
+    type Output = SimDuration;
+
+    fn sub(self, other: SimDuration) -> SimDuration {
+        self.checked_sub(other).expect(OVERFLOW)
+    }
+}
+
+impl Mul<i64> for SimDuration {
+    type Output = SimDuration;
+
+    fn mul(self, factor: i64) -> SimDuration {
+        self.checked_mul(factor).expect(OVERFLOW)
+    }
+}
+
+impl Neg for SimDuration {
+    type Output = SimDuration;
+
+    fn neg(self) -> SimDuration {
+        SimDuration(self.0.checked_neg().expect(OVERFLOW))
+    }
+}
+
+impl AddAssign for SimDuration {
+    fn add_assign(&mut self, other: SimDuration) {
+        *self = *self + other;
+    }
+}
+
+impl SubAssign for SimDuration {
+    fn sub_assign(&mut self, other: SimDuration) {
+        *self = *self - other;
+    }
+}
+
+impl std::iter::Sum for SimDuration {
+    fn sum<I: Iterator<Item = SimDuration>>(iter: I) -> SimDuration {
+        iter.fold(SimDuration::ZERO, Add::add)
+    }
+}
+
+// Both are shown as the bare number of ticks, in traces and logs
+impl fmt::Debug for SimTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.0, f)
+    }
+}
+
+impl fmt::Display for SimTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
+impl fmt::Debug for SimDuration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.0, f)
+    }
+}
+
+impl fmt::Display for SimDuration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_instants_and_durations() {
+        let arrival = SimTime::from(20);
+        let exit = arrival + SimDuration::from(45);
+        assert_eq!(exit, SimTime::from_ticks(65));
+        assert_eq!(exit - arrival, SimDuration::from(45));
+        assert_eq!(SimDuration::from(10) * 3 - SimDuration::from(5), SimDuration::from(25));
+
+        // Beyond the range of i32 ticks
+        let late = SimTime::from(i32::MAX) + SimDuration::from(i32::MAX);
+        assert_eq!(late.ticks(), 2 * i32::MAX as i64);
+        assert_eq!(format!("{} {:?}", late, SimDuration::from(7)), "4294967294 7");
+    }
+
+    #[test]
+    fn test_overflow_is_checked() {
+        assert_eq!(SimTime::MAX.checked_add(SimDuration::from(1)), None);
+        assert_eq!(SimDuration::MAX.checked_mul(2), None);
+        assert!(std::panic::catch_unwind(|| SimTime::MAX + SimDuration::from(1)).is_err());
+    }
+}
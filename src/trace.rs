@@ -0,0 +1,40 @@
+// In this file it is implemented the simulation trace: the
+// ordered record of every event handled by the event loop,
+// plus notes routines want to leave next to them.
+use crate::time::SimTime;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceEntry {
+    pub time: SimTime,
+    pub name: String,
+    pub detail: String,
+}
+
+#[derive(Debug, Default)]
+pub struct Trace {
+    entries: Vec<TraceEntry>,
+}
+
+impl Trace {
+    pub fn new() -> Self {
+        Trace { entries: Vec::new() }
+    }
+
+    pub fn record(&mut self, time: SimTime, name: String, detail: String) {
+        self.entries.push(TraceEntry { time, name, detail });
+    }
+
+    pub fn entries(&self) -> &[TraceEntry] {
+        &self.entries
+    }
+}
+
+impl fmt::Display for Trace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for entry in &self.entries {
+            writeln!(f, "{:>6} | {} | {}", entry.time, entry.name, entry.detail)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,62 @@
+// Golden trace tests: every scenario in populate_list is run to
+// completion on a virtual clock and its trace and statistics are
+// compared against tests/golden/<scenario>.txt.
+//
+// To re-bless the expected output after an intended change:
+//
+//     BLESS=1 cargo test --test golden
+use std::fs;
+use std::path::PathBuf;
+use PCS3446::populate_list::{populate_config, populate_list, SCENARIOS};
+use PCS3446::routines::create_event_to_routine;
+use PCS3446::system_abstractions::{ControlModule, SharedState};
+
+fn run_scenario(test_case: i32) -> String {
+    let event_to_routine = create_event_to_routine();
+    let shared_state = SharedState::from_config(populate_list(test_case), &populate_config(test_case));
+    let mut control_module = ControlModule::new(shared_state);
+
+    if let Err(failure) = control_module.run(&event_to_routine) {
+        panic!("{}", failure);
+    }
+
+    let trace = control_module.shared_state.get_trace();
+    let statistics = control_module.shared_state.get_statistics();
+    format!(
+        "Trace:\n{}\nEstatisticas:\n{}",
+        trace.lock().unwrap(),
+        statistics.lock().unwrap()
+    )
+}
+
+fn golden_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests").join("golden").join(format!("{}.txt", name))
+}
+
+#[test]
+fn test_scenarios_match_golden_output() {
+    let bless = std::env::var_os("BLESS").is_some();
+    let mut mismatches = Vec::new();
+
+    for (name, test_case) in SCENARIOS {
+        let actual = run_scenario(*test_case);
+        let path = golden_path(name);
+
+        if bless {
+            fs::write(&path, &actual).unwrap();
+            continue;
+        }
+
+        let expected = fs::read_to_string(&path)
+            .unwrap_or_else(|_| panic!("missing golden file {:?}, run with BLESS=1 to create it", path));
+        if expected != actual {
+            mismatches.push(format!("--- {} (expected)\n{}\n+++ {} (actual)\n{}", name, expected, name, actual));
+        }
+    }
+
+    assert!(
+        mismatches.is_empty(),
+        "golden output differs, run with BLESS=1 to update:\n{}",
+        mismatches.join("\n")
+    );
+}